@@ -0,0 +1,93 @@
+use std::cell::Cell;
+use std::fmt;
+use std::rc::Rc;
+use std::thread;
+use std::time::{Duration, Instant};
+use chrono::{DateTime, Local};
+
+/// Clock gives the time to sessions, tampering checks and the scheduler
+/// (tests use a ManualClock to simulate days in milliseconds)
+pub trait Clock: fmt::Debug {
+
+    /// Wall clock time (can jump : NTP, DST, user changing the date)
+    fn now(&self) -> DateTime<Local>;
+
+    /// Monotonic time (never jumps)
+    fn monotonic(&self) -> Instant;
+
+    fn sleep(&self, duration: Duration);
+}
+
+/// Clock shared by every component of the tracker
+pub type SharedClock = Rc<dyn Clock>;
+
+/// Clock of the operating system
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl SystemClock {
+    pub fn shared() -> SharedClock {
+        Rc::new(SystemClock)
+    }
+}
+
+impl Clock for SystemClock {
+
+    fn now(&self) -> DateTime<Local> {
+        Local::now()
+    }
+
+    fn monotonic(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        thread::sleep(duration);
+    }
+}
+
+/// Clock only moving when told to. Sleeping advances both times instantly,
+/// `set_now` changes the wall clock only (like a user changing the date).
+#[derive(Debug)]
+pub struct ManualClock {
+    wall: Cell<DateTime<Local>>,
+    start: Instant,
+    elapsed: Cell<Duration>,
+}
+
+impl ManualClock {
+
+    pub fn new(now: DateTime<Local>) -> Rc<Self> {
+        Rc::new(ManualClock {
+            wall: Cell::new(now),
+            start: Instant::now(),
+            elapsed: Cell::new(Duration::ZERO),
+        })
+    }
+
+    /// Let `duration` pass (on both clocks)
+    pub fn advance(&self, duration: Duration) {
+        self.elapsed.set(self.elapsed.get() + duration);
+        self.wall.set(self.wall.get() + duration);
+    }
+
+    /// Change the wall clock (the monotonic clock doesn't move)
+    pub fn set_now(&self, now: DateTime<Local>) {
+        self.wall.set(now);
+    }
+}
+
+impl Clock for ManualClock {
+
+    fn now(&self) -> DateTime<Local> {
+        self.wall.get()
+    }
+
+    fn monotonic(&self) -> Instant {
+        self.start + self.elapsed.get()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        self.advance(duration);
+    }
+}
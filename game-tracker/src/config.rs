@@ -0,0 +1,223 @@
+use std::env::home_dir;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use serde::Deserialize;
+use crate::errors::Error;
+use crate::process_source::ProcessSourceKind;
+use crate::session::{AllowedHours, Schedule};
+use crate::time::{DurationParser, WeekStart};
+use crate::tracker::{Games, ManualGame};
+
+/// Configuration shipped with the binary (used when no configuration file is found)
+pub const DEFAULT_CONFIG: &str = include_str!("../configs/linux.toml");
+
+const SYSTEM_CONFIG: &str = "/etc/game-tracker/config.toml";
+
+/// `[tracker]` section of the configuration file
+#[derive(Deserialize, Debug, Default, Clone)]
+pub struct TrackerSettings {
+
+    /// Delay between process scans (in seconds)
+    pub scan_interval: Option<u64>,
+
+    /// Delay between installed games scans (in seconds - 0 disables rescans)
+    pub rescan_interval: Option<u64>,
+
+    /// Monitor games only
+    pub monitor_only: Option<bool>,
+
+    /// Kill all games when tampering is detected
+    pub rampage_mode: Option<bool>,
+
+    /// How processes are read ("auto", "proc" or "sysinfo")
+    pub process_source: Option<ProcessSourceKind>,
+
+    /// File where every process scan is recorded
+    pub record: Option<PathBuf>,
+}
+
+/// `[session]` section of the configuration file
+#[derive(Deserialize, Debug, Default, Clone)]
+pub struct SessionSettings {
+
+    /// Session duration (ex.: "2h30m")
+    pub duration: Option<DurationParser>,
+
+    /// Time that can be played each week
+    pub weekly_duration: Option<DurationParser>,
+
+    /// Time that can be played each month
+    pub monthly_duration: Option<DurationParser>,
+
+    /// First day of the week of the weekly budget (default: "monday")
+    pub week_start: Option<WeekStart>,
+
+    /// Daily budget of some days of the week or dates (replaces `duration` on these days)
+    pub schedule: Option<Schedule>,
+
+    /// Hours when gaming is allowed, per day of the week or date
+    pub allowed_hours: Option<AllowedHours>,
+
+    /// Send warning of imminent session end
+    pub warn: Option<bool>,
+
+    /// Percentage of session played before sending warning
+    pub warning_threshold: Option<f64>,
+}
+
+/// Configuration file schema : settings, games declared manually and game locators (one per platform)
+#[derive(Deserialize, Debug, Default)]
+pub struct Config {
+
+    #[serde(default)]
+    pub tracker: TrackerSettings,
+
+    #[serde(default)]
+    pub session: SessionSettings,
+
+    #[serde(default)]
+    pub games: Vec<ManualGame>,
+
+    #[serde(flatten)]
+    pub platforms: Games,
+}
+
+impl Config {
+
+    pub fn parse(content: &str) -> Result<Config, Error> {
+        Ok(toml::from_str(content)?)
+    }
+
+    pub fn load(source: &ConfigSource) -> Result<Config, Error> {
+        Config::parse(&source.read()?)
+    }
+}
+
+/// Where the configuration is loaded from
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigSource {
+    File(PathBuf),
+    BuiltIn,
+}
+
+impl fmt::Display for ConfigSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigSource::File(path) => write!(f, "{}", path.display()),
+            ConfigSource::BuiltIn => write!(f, "built-in default configuration"),
+        }
+    }
+}
+
+impl ConfigSource {
+
+    /// Find the configuration file. If `path` is specified, it must exist.
+    /// Otherwise the first existing file is used (in order) :
+    /// - $XDG_CONFIG_HOME/game-tracker/config.toml (~/.config/game-tracker/config.toml)
+    /// - /etc/game-tracker/config.toml
+    /// - built-in default configuration
+    pub fn locate(path: Option<&Path>) -> Result<ConfigSource, Error> {
+        if let Some(path) = path {
+            return if path.is_file() {
+                Ok(ConfigSource::File(path.to_path_buf()))
+            } else {
+                Err(Error::ConfigNotFoundError(vec![path.to_path_buf()]))
+            };
+        }
+
+        let candidates = search_paths();
+        match candidates.iter().find(|candidate| candidate.is_file()) {
+            Some(found) => Ok(ConfigSource::File(found.clone())),
+            None => {
+                println!("No configuration file found (tried {:?}) - using built-in default configuration", candidates);
+                Ok(ConfigSource::BuiltIn)
+            }
+        }
+    }
+
+    /// Last modification of the configuration file (None for the built-in configuration)
+    pub fn modified(&self) -> Option<SystemTime> {
+        match self {
+            ConfigSource::File(path) => fs::metadata(path).and_then(|m| m.modified()).ok(),
+            ConfigSource::BuiltIn => None,
+        }
+    }
+
+    pub fn read(&self) -> Result<String, Error> {
+        match self {
+            ConfigSource::File(path) => Ok(fs::read_to_string(path)?),
+            ConfigSource::BuiltIn => Ok(DEFAULT_CONFIG.to_string()),
+        }
+    }
+}
+
+fn search_paths() -> Vec<PathBuf> {
+    let mut paths = vec![];
+
+    let config_home = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|d| !d.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| home_dir().map(|home| home.join(".config")));
+
+    if let Some(config_home) = config_home {
+        paths.push(config_home.join("game-tracker/config.toml"));
+    }
+
+    paths.push(PathBuf::from(SYSTEM_CONFIG));
+    paths
+}
+
+#[cfg(test)]
+mod config_tests {
+    use super::*;
+
+    #[test]
+    fn test_default_config() {
+        let config = Config::parse(DEFAULT_CONFIG).expect("valid configuration");
+        assert!(config.platforms.contains_key("steam"));
+    }
+
+    #[test]
+    fn test_settings_sections() {
+        let config = Config::parse(r#"
+            [tracker]
+            scan_interval = 30
+            monitor_only = true
+
+            [session]
+            duration = "2h30m"
+            weekly_duration = "10h"
+            week_start = "sunday"
+            warning_threshold = 80.0
+
+            [session.schedule]
+            saturday = "4h"
+            "2026-12-25" = "6h"
+
+            [session.allowed_hours]
+            default = ["18:00-23:00"]
+
+            [steam]
+            locator_type = "STEAM"
+        "#).expect("valid configuration");
+
+        assert_eq!(config.tracker.scan_interval, Some(30));
+        assert_eq!(config.tracker.monitor_only, Some(true));
+        assert_eq!(config.tracker.rampage_mode, None);
+        assert_eq!(config.session.duration.map(|d| d.to_seconds()), Some(9000));
+        assert_eq!(config.session.weekly_duration.map(|d| d.to_seconds()), Some(36000));
+        assert_eq!(config.session.monthly_duration, None);
+        assert_eq!(config.session.week_start.map(|w| w.weekday()), Some(chrono::Weekday::Sun));
+        assert_eq!(config.session.warning_threshold, Some(80.0));
+        let schedule = config.session.schedule.expect("schedule set");
+        assert_eq!(schedule.duration(chrono::NaiveDate::from_ymd_opt(2026, 12, 26).unwrap()), Some(chrono::Duration::hours(4)));
+        assert_eq!(schedule.duration(chrono::NaiveDate::from_ymd_opt(2026, 12, 25).unwrap()), Some(chrono::Duration::hours(6)));
+        let allowed_hours = config.session.allowed_hours.expect("allowed hours set");
+        assert_eq!(allowed_hours.windows(chrono::NaiveDate::from_ymd_opt(2026, 12, 26).unwrap()).map(<[_]>::len), Some(1));
+        assert_eq!(config.platforms.len(), 1);
+
+        assert!(Config::parse("[session]\nweek_start = \"someday\"").is_err());
+    }
+}
@@ -1,36 +1,76 @@
-use chrono::{DateTime, Duration, Local};
-use rusqlite::{params, Connection};
+use std::path::Path;
+use chrono::{Duration, NaiveDate};
+use rusqlite::{params, Connection, OptionalExtension};
 use crate::errors::Error;
 use crate::process_tree::ProcessInfo;
 use crate::subtasks::SubTask;
-use crate::tracker::GamingTracker;
+use crate::tracker::{Game, GamingTracker};
 
-pub fn init_database() -> Result<Connection, Error> {
-    let conn = Connection::open("statistics.sqlite")?;
+/// Statistics database (in the working directory)
+const DATABASE: &str = "statistics.sqlite";
+
+pub fn init_database(path: &Path) -> Result<Connection, Error> {
+    let conn = Connection::open(path)?;
     conn.execute(
         "CREATE TABLE IF NOT EXISTS game_tracker (
             pid INTEGER NOT NULL,
             name TEXT,
             cmd TEXT,
             game_name TEXT,
+            game_id TEXT,
             run_time INTEGER NOT NULL,
             start_time DATETIME NOT NULL,
             PRIMARY KEY (pid, name, cmd, start_time)
     )", ())?;
 
+    // databases created before game ids were tracked
+    add_column_if_missing(&conn, "game_tracker", "game_id", "TEXT")?;
+
+    // time played each day (games played at the same time are counted once)
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS daily_sessions (
+            day DATE NOT NULL PRIMARY KEY,
+            time_played INTEGER NOT NULL
+    )", ())?;
+
+    // time played each day by each game (games' limits are daily) - game_id is '' without id
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS daily_games (
+            day DATE NOT NULL,
+            game_name TEXT NOT NULL,
+            game_id TEXT NOT NULL,
+            time_played INTEGER NOT NULL,
+            PRIMARY KEY (day, game_name, game_id)
+    )", ())?;
 
     Ok(conn)
 }
 
+fn add_column_if_missing(conn: &Connection, table: &str, column: &str, column_type: &str) -> Result<(), Error> {
+    let mut statement = conn.prepare(format!("PRAGMA table_info({})", table).as_str())?;
+    let columns = statement.query_map((), |row| row.get::<_, String>(1))?
+        .collect::<Result<Vec<String>, _>>()?;
+
+    if !columns.iter().any(|c| c == column) {
+        conn.execute(format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, column_type).as_str(), ())?;
+    }
+
+    Ok(())
+}
+
 pub struct SaveStatistics {
     db_connection: Connection,
 }
 
 impl SaveStatistics {
     pub fn new() -> Result<Box<Self>, Error> {
+        SaveStatistics::open(Path::new(DATABASE))
+    }
+
+    pub fn open(path: &Path) -> Result<Box<Self>, Error> {
         Ok(Box::new(
             Self {
-                db_connection: init_database()?
+                db_connection: init_database(path)?
             }
         ))
     }
@@ -38,42 +78,162 @@ impl SaveStatistics {
 
 impl SubTask for SaveStatistics {
     fn execute(&mut self, tracker: &mut GamingTracker) -> Result<(), Error> {
-        for (name, processes) in tracker.gametime_tracker() {
-            for process in processes {
-                self.upsert_process(&process, name)?;
+        for (game, game_time) in tracker.gametime_tracker() {
+            for process in game_time.processes() {
+                self.upsert_process(process, game)?;
             }
         }
 
+        // time played until midnight (the tracker's totals are already reset for the new day)
+        if let Some(ended_day) = tracker.take_ended_day() {
+            for (game, time_played) in ended_day.games() {
+                self.save_game_time_played(ended_day.day(), game, *time_played)?;
+            }
+
+            if tracker.session().is_some() {
+                self.save_time_played(ended_day.day(), ended_day.time_played())?;
+            }
+        }
+
+        // restored when the tracker restarts (see `restore`)
+        for (game, game_time) in tracker.gametime_tracker() {
+            self.save_game_time_played(tracker.day(), game, game_time.time_played())?;
+        }
+
+        if let Some(session) = tracker.session() {
+            self.save_time_played(session.day(), tracker.total_time_played())?;
+        }
+
         Ok(())
     }
 }
 
 impl SaveStatistics {
 
-    pub fn upsert_process(&self, proc: &ProcessInfo, game_name: &str) -> Result<(), Error> {
+    pub fn upsert_process(&self, proc: &ProcessInfo, game: &Game) -> Result<(), Error> {
         let mut statement = self.db_connection.prepare("
-            INSERT INTO game_tracker (pid, name, cmd, game_name, run_time, start_time)
-                VALUES (?1, ?2, ?3, ?4, $5, DATETIME(?6, 'unixepoch'))
-            ON CONFLICT (pid, name, cmd, start_time) DO UPDATE SET run_time = ?5
+            INSERT INTO game_tracker (pid, name, cmd, game_name, game_id, run_time, start_time)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, DATETIME(?7, 'unixepoch'))
+            ON CONFLICT (pid, name, cmd, start_time) DO UPDATE SET run_time = ?6
         ")?;
 
-        statement.execute(params![proc.pid().as_u32(), proc.name(), proc.cmd(), game_name,
-        proc.run_time() as i64, proc.start_time() as i64])?;
+        statement.execute(params![proc.pid().as_u32(), proc.name(), proc.cmd(), game.name(),
+        game.id(), proc.run_time() as i64, proc.start_time() as i64])?;
 
         Ok(())
     }
 
-    pub fn time_played_by_date(&self, date: DateTime<Local>) -> Result<Duration, Error> {
-        let mut statement = self.db_connection.prepare("
-            SELECT SUM(run_time) as total
-            FROM game_tracker
-            WHERE date(game_tracker.start_time) like date(?1)
-        ")?;
-        let total: i64 = statement.query_one(
-            params![date.to_rfc3339()],
+    pub fn save_time_played(&self, day: NaiveDate, time_played: Duration) -> Result<(), Error> {
+        self.db_connection.execute("
+            INSERT INTO daily_sessions (day, time_played) VALUES (?1, ?2)
+            ON CONFLICT (day) DO UPDATE SET time_played = ?2
+        ", params![day, time_played.num_seconds()])?;
+
+        Ok(())
+    }
+
+    pub fn save_game_time_played(&self, day: NaiveDate, game: &Game, time_played: Duration) -> Result<(), Error> {
+        self.db_connection.execute("
+            INSERT INTO daily_games (day, game_name, game_id, time_played) VALUES (?1, ?2, ?3, ?4)
+            ON CONFLICT (day, game_name, game_id) DO UPDATE SET time_played = ?4
+        ", params![day, game.name(), game.id().unwrap_or_default(), time_played.num_seconds()])?;
+
+        Ok(())
+    }
+
+    /// Restore today's time played (of each game, and of the session) and the time played
+    /// earlier in the period of each budget (the session continues where it was when the
+    /// tracker stopped, games' limits too)
+    pub fn restore(&self, tracker: &mut GamingTracker) -> Result<(), Error> {
+        for (name, id, time_played) in self.games_played_by_date(tracker.day())? {
+            tracker.restore_game_time_played(&name, id.as_deref(), time_played);
+        }
+
+        let Some(session) = tracker.session() else {
+            return Ok(());
+        };
+
+        let day = session.day();
+        let played_before = session.budgets().iter()
+            .map(|budget| {
+                let played = self.time_played_between(session.period_start(budget.period()), day)?;
+                Ok((budget.period(), played))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        tracker.restore_time_played(self.time_played_by_date(day)?);
+        if let Some(session) = tracker.session_mut() {
+            for (period, played) in played_before {
+                session.restore_played(period, played);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Time played from `from` to the day before `to`
+    pub fn time_played_between(&self, from: NaiveDate, to: NaiveDate) -> Result<Duration, Error> {
+        let total: i64 = self.db_connection.query_row(
+            "SELECT COALESCE(SUM(time_played), 0) FROM daily_sessions WHERE day >= ?1 AND day < ?2",
+            params![from, to],
             |row| row.get(0)
         )?;
 
         Ok(Duration::seconds(total))
     }
-}
\ No newline at end of file
+
+    /// Time played on `day` by each game : name, id (if the game has one) and time played
+    pub fn games_played_by_date(&self, day: NaiveDate) -> Result<Vec<(String, Option<String>, Duration)>, Error> {
+        let mut statement = self.db_connection.prepare(
+            "SELECT game_name, game_id, time_played FROM daily_games WHERE day = ?1"
+        )?;
+
+        let games = statement.query_map(params![day], |row| {
+            let id: String = row.get(1)?;
+            Ok((row.get(0)?, Some(id).filter(|id| !id.is_empty()), Duration::seconds(row.get(2)?)))
+        })?;
+
+        Ok(games.collect::<Result<Vec<_>, _>>()?)
+    }
+
+    /// Time played on `day` (zero if the tracker didn't run that day)
+    pub fn time_played_by_date(&self, day: NaiveDate) -> Result<Duration, Error> {
+        let total: Option<i64> = self.db_connection.query_row(
+            "SELECT time_played FROM daily_sessions WHERE day = ?1",
+            params![day],
+            |row| row.get(0)
+        ).optional()?;
+
+        Ok(Duration::seconds(total.unwrap_or_default()))
+    }
+}
+
+#[cfg(test)]
+mod db_tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    #[test]
+    fn test_time_played_by_date() {
+        let directory = TempDir::new("statistics");
+        let path = directory.path().join(DATABASE);
+        let statistics = SaveStatistics::open(&path).expect("no errors!");
+        let day = NaiveDate::from_ymd_opt(2026, 1, 10).unwrap();
+
+        assert_eq!(statistics.time_played_by_date(day).expect("no errors!"), Duration::zero());
+
+        statistics.save_time_played(day, Duration::minutes(20)).expect("no errors!");
+        statistics.save_time_played(day, Duration::minutes(25)).expect("no errors!");
+        drop(statistics);
+
+        let statistics = SaveStatistics::open(&path).expect("no errors!");
+        assert_eq!(statistics.time_played_by_date(day).expect("no errors!"), Duration::minutes(25));
+        assert_eq!(statistics.time_played_by_date(day.succ_opt().unwrap()).expect("no errors!"), Duration::zero());
+
+        statistics.save_time_played(day.pred_opt().unwrap(), Duration::minutes(30)).expect("no errors!");
+        let week = statistics.time_played_between(day - Duration::days(5), day.succ_opt().unwrap());
+        assert_eq!(week.expect("no errors!"), Duration::minutes(55));
+        let before = statistics.time_played_between(day - Duration::days(5), day);
+        assert_eq!(before.expect("no errors!"), Duration::minutes(30));
+    }
+}
@@ -0,0 +1,218 @@
+use std::path::Path;
+
+/// DesktopEntry represents the `[Desktop Entry]` group of a freedesktop .desktop file
+/// (only the keys used to find games are kept).
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct DesktopEntry {
+    entry_type: String,
+    name: String,
+    exec: Option<String>,
+    try_exec: Option<String>,
+    categories: Vec<String>,
+    hidden: bool,
+}
+
+/// Split an Exec value into arguments (quoting rules of the desktop entry specification)
+fn split_exec(exec: &str) -> Vec<String> {
+    let mut arguments = vec![];
+    let mut current = String::new();
+    let mut in_argument = false;
+    let mut quoted = false;
+    let mut chars = exec.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                in_argument = true;
+            },
+            '\\' if quoted => {
+                if let Some(escaped) = chars.next() {
+                    current.push(escaped);
+                }
+            },
+            c if c.is_whitespace() && !quoted => {
+                if in_argument {
+                    arguments.push(std::mem::take(&mut current));
+                    in_argument = false;
+                }
+            },
+            _ => {
+                current.push(c);
+                in_argument = true;
+            }
+        }
+    }
+
+    if in_argument {
+        arguments.push(current);
+    }
+
+    arguments
+}
+
+/// Field codes (%f, %U, ...) are replaced by the launcher - they aren't part of the command
+fn is_field_code(argument: &str) -> bool {
+    argument.len() == 2 && argument.starts_with('%') && argument != "%%"
+}
+
+impl DesktopEntry {
+
+    /// Parse the content of a .desktop file (returns None if there is no valid `[Desktop Entry]` group)
+    pub fn parse(content: &str) -> Option<DesktopEntry> {
+        let mut entry = DesktopEntry::default();
+        let mut in_desktop_entry = false;
+        let mut found = false;
+
+        for line in content.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if line.starts_with('[') {
+                in_desktop_entry = line == "[Desktop Entry]";
+                found |= in_desktop_entry;
+                continue;
+            }
+
+            if !in_desktop_entry {
+                continue;
+            }
+
+            // localized keys (ex.: Name[fr]) are ignored
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+
+            let value = value.trim().to_string();
+            match key.trim() {
+                "Type" => entry.entry_type = value,
+                "Name" => entry.name = value,
+                "Exec" => entry.exec = Some(value),
+                "TryExec" => entry.try_exec = Some(value),
+                "Categories" => entry.categories = value.split(';')
+                    .filter(|c| !c.is_empty())
+                    .map(str::to_string)
+                    .collect(),
+                "Hidden" => entry.hidden = value == "true",
+                _ => ()
+            }
+        }
+
+        if found && !entry.name.is_empty() {
+            Some(entry)
+        } else {
+            None
+        }
+    }
+
+    pub fn name(&self) -> &str { &self.name }
+
+    pub fn try_exec(&self) -> Option<&str> { self.try_exec.as_deref() }
+
+    /// Checks if the entry is an application categorised as a game
+    pub fn is_game(&self) -> bool {
+        self.entry_type == "Application"
+            && !self.hidden
+            && self.categories.iter().any(|c| c == "Game")
+    }
+
+    /// Arguments of the Exec key, starting with the program (environment assignments, `env`
+    /// and field codes are skipped)
+    fn exec_arguments(&self) -> Vec<String> {
+        let arguments = split_exec(self.exec.as_deref().unwrap_or_default());
+
+        arguments.into_iter()
+            .filter(|argument| !is_field_code(argument))
+            .skip_while(|argument| {
+                let is_env = Path::new(argument).file_name().is_some_and(|name| name == "env");
+                let is_assignment = argument.split_once('=')
+                    .is_some_and(|(name, _)| !name.is_empty() && !name.contains('/'));
+
+                is_env || is_assignment
+            })
+            .collect()
+    }
+
+    /// Arguments given to `flatpak` if the entry launches a Flatpak application
+    fn flatpak_arguments(&self) -> Option<Vec<String>> {
+        let mut arguments = self.exec_arguments();
+        let is_flatpak = arguments.first()
+            .is_some_and(|program| Path::new(program).file_name().is_some_and(|name| name == "flatpak"));
+
+        is_flatpak.then(|| arguments.split_off(1))
+    }
+
+    /// Program executed by the Exec key (environment assignments and `env` are skipped).
+    /// For Flatpak applications, this is the command run inside the sandbox (if specified).
+    pub fn exec_program(&self) -> Option<String> {
+        let command = self.flatpak_arguments()
+            .and_then(|arguments| arguments.iter()
+                .find_map(|argument| argument.strip_prefix("--command=").map(str::to_string)));
+
+        command.or_else(|| self.exec_arguments().into_iter().next())
+    }
+
+    /// Application id of the Flatpak application launched by the entry
+    /// (ex.: `flatpak run --branch=stable com.valvesoftware.Steam` is com.valvesoftware.Steam)
+    pub fn flatpak_app_id(&self) -> Option<String> {
+        let arguments = self.flatpak_arguments()?;
+        let mut arguments = arguments.iter()
+            .filter(|argument| !argument.starts_with('-'));
+
+        if arguments.next().is_some_and(|command| command == "run") {
+            // an application can be run with a branch (ex.: com.valvesoftware.Steam//beta)
+            arguments.next().and_then(|app| app.split('/').next()).map(str::to_string)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod desktop_entry_tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_game_entry() {
+        let entry = DesktopEntry::parse(r#"
+            [Desktop Entry]
+            Type=Application
+            Name=SuperTux
+            Name[fr]=SuperTux (fr)
+            Exec=env SDL_VIDEODRIVER=x11 "/opt/super tux/bin/supertux2" %U
+            TryExec=supertux2
+            Categories=Game;ArcadeGame;
+
+            [Desktop Action Editor]
+            Name=Level editor
+            Exec=supertux2 --editor
+        "#).expect("valid desktop entry");
+
+        assert!(entry.is_game());
+        assert_eq!(entry.name(), "SuperTux");
+        assert_eq!(entry.try_exec(), Some("supertux2"));
+        assert_eq!(entry.exec_program(), Some("/opt/super tux/bin/supertux2".to_string()));
+    }
+
+    #[test]
+    fn test_parse_non_game_entries() {
+        let editor = DesktopEntry::parse("[Desktop Entry]\nType=Application\nName=Editor\nExec=vim %F\nCategories=Utility;")
+            .expect("valid desktop entry");
+        assert!(!editor.is_game());
+
+        let hidden = DesktopEntry::parse("[Desktop Entry]\nType=Application\nName=Old\nHidden=true\nCategories=Game;")
+            .expect("valid desktop entry");
+        assert!(!hidden.is_game());
+
+        let flatpak = DesktopEntry::parse(
+            "[Desktop Entry]\nName=SuperTux\nExec=/usr/bin/flatpak run --command=supertux2 org.supertuxproject.SuperTux"
+        ).expect("valid desktop entry");
+        assert_eq!(flatpak.exec_program(), Some("supertux2".to_string()));
+        assert_eq!(flatpak.flatpak_app_id(), Some("org.supertuxproject.SuperTux".to_string()));
+        assert_eq!(editor.flatpak_app_id(), None);
+
+        assert_eq!(DesktopEntry::parse("[Desktop Action Play]\nName=Play"), None);
+        assert_eq!(DesktopEntry::parse("[Desktop Entry]\nType=Application"), None);
+    }
+}
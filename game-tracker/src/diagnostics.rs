@@ -0,0 +1,359 @@
+use std::collections::{HashMap, HashSet};
+use std::io::IsTerminal;
+use sysinfo::{Pid, Uid, Users};
+use toml::{Table, Value};
+use crate::config::{Config, ConfigSource};
+use crate::errors::Error;
+use crate::matchers::Rule;
+use crate::process_tree::{ProcessInfo, ProcessTree};
+use crate::tracker::{Game, GamingTracker};
+
+const TRACKER_KEYS: &[&str] = &["scan_interval", "rescan_interval", "monitor_only", "rampage_mode", "process_source", "record"];
+
+const SESSION_KEYS: &[&str] = &["duration", "weekly_duration", "monthly_duration", "week_start", "schedule", "allowed_hours", "warn", "warning_threshold"];
+
+const GAME_KEYS: &[&str] = &["name", "category", "limit"];
+
+const PLATFORM_KEYS: &[&str] = &[
+    "home_paths", "absolute_paths", "locator_type", "search_entity_type",
+    "flatpak_ids", "snap_names", "ignore", "matchers", "game_matchers",
+];
+
+const MATCHER_KEYS: &[&str] = &[
+    "process_names", "exe_prefixes", "cmd_regexes", "cwd_prefixes", "exclude", "substring_match",
+];
+
+/// Substrings shorter than this are likely to match unrelated command lines
+const MIN_SUBSTRING_LENGTH: usize = 4;
+
+/// Report the keys of `table` which aren't in `known` (as dotted paths)
+fn check_keys(table: &Table, prefix: &str, known: &[&str], unknown: &mut Vec<String>) {
+    unknown.extend(table.keys()
+        .filter(|key| !known.contains(&key.as_str()))
+        .map(|key| format!("{}{}", prefix, key)));
+}
+
+fn check_matcher_keys(value: &Value, prefix: &str, unknown: &mut Vec<String>) {
+    if let Some(table) = value.as_table() {
+        check_keys(table, prefix, MATCHER_KEYS, unknown);
+    }
+}
+
+/// Keys of the configuration file which are ignored by the tracker (typos, removed settings...)
+pub fn unknown_keys(content: &str) -> Result<Vec<String>, Error> {
+    let document: Table = toml::from_str(content)?;
+    let mut unknown = vec![];
+
+    for (key, value) in document.iter() {
+        match (key.as_str(), value) {
+            ("tracker", Value::Table(tracker)) => check_keys(tracker, "tracker.", TRACKER_KEYS, &mut unknown),
+            ("session", Value::Table(session)) => check_keys(session, "session.", SESSION_KEYS, &mut unknown),
+            ("games", Value::Array(games)) => {
+                let known = [GAME_KEYS, MATCHER_KEYS].concat();
+                for (i, game) in games.iter().enumerate() {
+                    if let Some(game) = game.as_table() {
+                        check_keys(game, &format!("games[{}].", i), &known, &mut unknown);
+                    }
+                }
+            },
+            (platform, Value::Table(locator)) => {
+                check_keys(locator, &format!("{}.", platform), PLATFORM_KEYS, &mut unknown);
+
+                if let Some(matchers) = locator.get("matchers") {
+                    check_matcher_keys(matchers, &format!("{}.matchers.", platform), &mut unknown);
+                }
+
+                if let Some(Value::Table(game_matchers)) = locator.get("game_matchers") {
+                    for (game, matchers) in game_matchers.iter() {
+                        let prefix = format!("{}.game_matchers.\"{}\".", platform, game);
+                        check_matcher_keys(matchers, &prefix, &mut unknown);
+                    }
+                }
+            },
+            _ => unknown.push(key.clone()),
+        }
+    }
+
+    Ok(unknown)
+}
+
+/// Substring rules of the game that may cause false matches
+fn short_substrings(game: &Game) -> Vec<&str> {
+    if !game.matcher().substring_enabled() {
+        return vec![];
+    }
+
+    game.matcher().rules().iter()
+        .filter_map(|rule| match rule {
+            Rule::Substring(s) if s.trim().chars().count() < MIN_SUBSTRING_LENGTH => Some(s.as_str()),
+            _ => None,
+        })
+        .collect()
+}
+
+fn print_table(rows: &[[String; 3]]) {
+    let header = ["PLATFORM".to_string(), "GAME".to_string(), "MATCHERS".to_string()];
+    let width = |column: usize| rows.iter()
+        .chain([&header])
+        .map(|row| row[column].chars().count())
+        .max()
+        .unwrap_or(0);
+    let (platform_width, game_width) = (width(0), width(1));
+
+    for row in [&header].into_iter().chain(rows) {
+        println!("{:platform_width$}  {:game_width$}  {}", row[0], row[1], row[2]);
+    }
+}
+
+/// Validate the configuration and print the games found with their matchers.
+/// Returns false if a problem was found (unknown keys or substrings likely to match unrelated processes).
+pub fn check_config(source: &ConfigSource) -> Result<bool, Error> {
+    println!("Checking configuration from {}", source);
+    let content = source.read()?;
+    let mut valid = true;
+
+    for key in unknown_keys(&content)? {
+        println!("Unknown key: {}", key);
+        valid = false;
+    }
+
+    let config = Config::parse(&content)?;
+    for (platform, locator) in config.platforms.iter() {
+        for path in locator.missing_paths() {
+            println!("Path not found: [{}] {}", platform, path.display());
+        }
+    }
+
+    let tracker = GamingTracker::try_from(config)?;
+    let platforms = tracker.platforms().iter()
+        .flat_map(|(platform, locator)| locator.games().iter().map(move |game| (platform.as_str(), game)));
+    let games: Vec<(&str, &Game)> = tracker.manual_games().iter()
+        .map(|game| ("games", game))
+        .chain(platforms)
+        .collect();
+
+    let mut rows = vec![];
+    let mut warnings = vec![];
+    for (platform, game) in games.iter() {
+        let name = match game.id() {
+            Some(id) => format!("{} ({})", game.name(), id),
+            None => game.name().to_string(),
+        };
+
+        let mut matchers: Vec<String> = game.matcher().rules().iter()
+            .map(Rule::to_string)
+            .collect();
+        if !game.matcher().substring_enabled() {
+            matchers.push("substring matching disabled".to_string());
+        }
+
+        rows.push([platform.to_string(), name, matchers.join(", ")]);
+
+        for substring in short_substrings(game) {
+            warnings.push(format!(
+                "Warning: [{}] \"{}\" is matched by the short substring \"{}\" - \
+                 unrelated processes may be counted (add exact matchers or set substring_match = false)",
+                platform, game.name(), substring
+            ));
+        }
+    }
+
+    println!();
+    print_table(&rows);
+    println!();
+    println!("{} game(s) found", rows.len());
+
+    for warning in warnings.iter() {
+        println!("{}", warning);
+    }
+
+    Ok(valid && warnings.is_empty())
+}
+
+/// Processes shown by `list-processes` (every filter must match)
+#[derive(Debug, Default)]
+pub struct ProcessFilter {
+
+    /// User name or id
+    pub user: Option<String>,
+
+    /// Text searched (case-insensitive) in the name and command line
+    pub name: Option<String>,
+
+    /// Only show this process and its children
+    pub pid: Option<u32>,
+}
+
+impl ProcessFilter {
+
+    fn matches(&self, p: &ProcessInfo, user_id: Option<&Uid>) -> bool {
+        let user = self.user.is_none() || (user_id.is_some() && p.user_id() == user_id);
+        let name = self.name.as_ref().is_none_or(|name| {
+            let name = name.to_lowercase();
+            p.name().to_lowercase().contains(&name) || p.cmd().to_lowercase().contains(&name)
+        });
+
+        user && name
+    }
+}
+
+/// Id of the user named `user` (numeric ids are accepted)
+fn find_user_id(users: &Users, user: &str) -> Option<Uid> {
+    users.iter()
+        .find(|u| u.name() == user)
+        .map(|u| u.id().clone())
+        .or_else(|| user.parse::<Uid>().ok())
+}
+
+/// Checks if the process is `ancestor` or one of its descendants
+fn is_in_subtree(tree: &ProcessTree, p: &ProcessInfo, ancestor: u32) -> bool {
+    std::iter::successors(Some(p), |process| tree.parent(process.pid()))
+        .any(|process| process.pid().as_u32() == ancestor)
+}
+
+/// Pids of the processes shown by `list-processes` : processes matching the filters
+/// and their parents (so the tree stays readable)
+fn select_processes(tree: &ProcessTree, filter: &ProcessFilter, user_id: Option<&Uid>) -> HashSet<Pid> {
+    let mut shown = HashSet::new();
+    let selected = tree.iter()
+        .filter(|p| filter.pid.is_none_or(|pid| is_in_subtree(tree, p, pid)))
+        .filter(|p| filter.matches(p, user_id));
+
+    for process in selected {
+        let mut current = Some(process);
+        while let Some(process) = current && shown.insert(process.pid()) {
+            current = tree.parent(process.pid());
+        }
+    }
+
+    shown
+}
+
+/// Print the running processes as a tree, marking the processes the tracking counts as games
+/// (with their matching rule) and the other processes a game's rules match
+pub fn list_processes(source: &ConfigSource, filter: &ProcessFilter) -> Result<(), Error> {
+    let mut tracker = GamingTracker::try_from(Config::load(source)?)?;
+    tracker.refresh()?;
+
+    let users = Users::new_with_refreshed_list();
+    let user_id = filter.user.as_deref().and_then(|user| find_user_id(&users, user));
+    if let Some(user) = filter.user.as_deref() && user_id.is_none() {
+        println!("Unknown user: {}", user);
+    }
+
+    let tree = tracker.process_tree();
+    let shown = select_processes(tree, filter, user_id.as_ref());
+    let highlight = std::io::stdout().is_terminal();
+
+    // processes the tracking counts (one per running game - its children belong to the game)
+    let counted: HashMap<Pid, (&Game, &Rule)> = tracker.running_games().into_iter()
+        .map(|(game, process, rule)| (process.pid(), (game, rule)))
+        .collect();
+
+    let describe = |p: &ProcessInfo| {
+        if !shown.contains(&p.pid()) {
+            return None;
+        }
+
+        let user = p.user_id()
+            .and_then(|id| users.get_user_by_id(id))
+            .map(|u| u.name().to_string())
+            .unwrap_or_else(|| "?".to_string());
+        let line = format!("[{}] {}", user, p.cmd());
+
+        if let Some((game, rule)) = counted.get(&p.pid()) {
+            let line = format!("{} <== {} ({}: {})", line, game.name(), rule, rule.reason(p));
+            return Some(if highlight { format!("\x1b[1;32m{}\x1b[0m", line) } else { format!("* {}", line) });
+        }
+
+        // processes matching a game by themselves, but not counted (children of a game,
+        // launchers containing a game, substrings in a tree where an exact rule matched...)
+        Some(match tracker.matching_game(p) {
+            Some((game, rule)) => format!("{} (would also match {} - {}: {})", line, game.name(), rule, rule.reason(p)),
+            None if p.is_sandbox_wrapper() => format!("{} (sandbox wrapper - never matched)", line),
+            None => line,
+        })
+    };
+
+    for process in tree.roots() {
+        print!("{}", tree.to_string(process.pid(), &describe));
+    }
+    println!("{} processes ({} shown)", tree.len(), shown.len());
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod diagnostics_tests {
+    use super::*;
+    use crate::config::DEFAULT_CONFIG;
+
+    #[test]
+    fn test_default_config_has_no_unknown_keys() {
+        let unknown = unknown_keys(DEFAULT_CONFIG).expect("no errors!");
+        assert!(unknown.is_empty(), "unknown keys: {:?}", unknown);
+    }
+
+    #[test]
+    fn test_unknown_keys() {
+        let unknown = unknown_keys(r#"
+            verbose = true
+
+            [tracker]
+            scan_intervall = 30
+
+            [[games]]
+            name = "Minecraft"
+            process_name = ["java"]
+
+            [steam]
+            locator_type = "STEAM"
+            home_path = [".steam/steam"]
+
+            [steam.matchers]
+            exclude = ["steamwebhelper"]
+
+            [steam.game_matchers."Portal 2"]
+            substring = false
+        "#).expect("no errors!");
+
+        assert_eq!(unknown, vec![
+            "games[0].process_name",
+            "steam.home_path",
+            "steam.game_matchers.\"Portal 2\".substring",
+            "tracker.scan_intervall",
+            "verbose",
+        ]);
+    }
+
+    #[test]
+    fn test_short_substrings() {
+        let short = Game::from_name("Hex");
+        assert_eq!(short_substrings(&short), vec!["Hex"]);
+
+        let long = Game::from_name("Hexcells");
+        assert!(short_substrings(&long).is_empty());
+    }
+
+    #[test]
+    fn test_process_filter() {
+        let tree = ProcessTree::build(vec![
+            (ProcessInfo::fake(10, "steam", &["steam"]), None),
+            (ProcessInfo::fake(11, "reaper", &["reaper", "SteamLaunch"]), Some(Pid::from_u32(10))),
+            (ProcessInfo::fake(12, "hl2_linux", &["/games/Portal/hl2_linux"]), Some(Pid::from_u32(11))),
+            (ProcessInfo::fake(13, "steamwebhelper", &["steamwebhelper"]), Some(Pid::from_u32(10))),
+            (ProcessInfo::fake(20, "bash", &["bash"]), None),
+        ]);
+
+        let by_name = ProcessFilter { name: Some("PORTAL".to_string()), ..Default::default() };
+        let shown = select_processes(&tree, &by_name, None);
+        assert_eq!(shown, HashSet::from([10, 11, 12].map(Pid::from_u32)));
+
+        let by_pid = ProcessFilter { pid: Some(11), ..Default::default() };
+        let shown = select_processes(&tree, &by_pid, None);
+        assert_eq!(shown, HashSet::from([10, 11, 12].map(Pid::from_u32)));
+
+        let by_user = ProcessFilter { user: Some("nobody".to_string()), ..Default::default() };
+        assert!(select_processes(&tree, &by_user, None).is_empty());
+    }
+}
@@ -1,8 +1,10 @@
 use std::num::ParseFloatError;
+use std::path::PathBuf;
 use std::time::SystemTimeError;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum Error {
 
     #[error("could not parse session duration")]
@@ -11,12 +13,33 @@ pub enum Error {
     #[error("could not deserialize toml file")]
     TOMLDeserializeError(#[from] toml::de::Error),
 
+    #[error("could not find configuration file (tried {0:?})")]
+    ConfigNotFoundError(Vec<PathBuf>),
+
+    #[error("could not parse VDF file: {0}")]
+    VDFParseError(String),
+
+    #[error("could not parse day of the week (ex.: \"monday\", \"sun\")")]
+    WeekdayParserError,
+
+    #[error("invalid schedule day \"{0}\" (expected a day of the week or a date like \"2026-12-25\")")]
+    ScheduleParserError(String),
+
+    #[error("invalid allowed hours \"{0}\" (expected a day of the week, a date or \"default\" with windows like \"18:00-23:00\")")]
+    AllowedHoursParserError(String),
+
     #[error("could not calculate when tomorrow is")]
     CalculateEndOfDayError,
 
     #[error("threshold value must be between 0 and 100")]
     InvalidThresholdError,
 
+    #[error("invalid recording: {0}")]
+    RecordingError(String),
+
+    #[error("end of recording")]
+    EndOfRecordingError,
+
     #[error(transparent)]
     ParseFloatError(#[from] ParseFloatError),
 
@@ -26,9 +49,15 @@ pub enum Error {
     #[error(transparent)]
     NotificationError(#[from]  notify_rust::error::Error),
 
+    #[error(transparent)]
+    JSONError(#[from] serde_json::Error),
+
     #[error(transparent)]
     RegexError(#[from] regex::Error),
 
+    #[error(transparent)]
+    AhoCorasickError(#[from] aho_corasick::BuildError),
+
     #[error(transparent)]
     ParseIntError(#[from] std::num::ParseIntError),
 
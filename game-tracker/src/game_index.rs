@@ -0,0 +1,247 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use aho_corasick::AhoCorasick;
+use sysinfo::Pid;
+use crate::errors::Error;
+use crate::matchers::{Precision, Rule};
+use crate::process_tree::{ProcessInfo, ProcessTree};
+use crate::tracker::Game;
+
+/// GameIndex finds the games a process may belong to without trying every game on every
+/// process. Substrings (game names, install directories in command lines) are compiled into
+/// a single Aho-Corasick automaton, so the name and command line of a process are scanned
+/// once; app ids, process names and paths are looked up in hash maps. The games found are
+/// only candidates : their rules are then checked normally (see `Matcher::matching_rule`).
+/// The index is built once per configuration load.
+#[derive(Debug, Default)]
+pub struct GameIndex {
+
+    /// Games in priority order (games declared manually first) - candidates are positions in this list
+    games: Vec<Game>,
+
+    /// Lowercase substrings searched in the name and command line of processes
+    automaton: Option<AhoCorasick>,
+
+    /// Game of each pattern of the automaton
+    pattern_games: Vec<usize>,
+
+    app_ids: HashMap<String, Vec<usize>>,
+
+    process_names: HashMap<String, Vec<usize>>,
+
+    /// Executables and directories (matched against the executable or working directory and their parents)
+    paths: HashMap<PathBuf, Vec<usize>>,
+
+    /// Games with rules that can't be indexed (regular expressions) - always candidates
+    unindexed: Vec<usize>,
+}
+
+impl GameIndex {
+
+    pub fn new(games: Vec<Game>) -> Result<GameIndex, Error> {
+        let mut index = GameIndex::default();
+        let mut patterns = vec![];
+
+        for (i, game) in games.iter().enumerate() {
+            for rule in game.matcher().rules() {
+                match rule {
+                    Rule::SteamAppId(app_id) => index.app_ids.entry(app_id.clone()).or_default().push(i),
+                    Rule::ProcessName(name) => index.process_names.entry(name.clone()).or_default().push(i),
+                    Rule::Executable(path) | Rule::ExePrefix(path) | Rule::CwdPrefix(path) => {
+                        index.paths.entry(path.clone()).or_default().push(i)
+                    },
+                    Rule::InstallDir(directory) => {
+                        index.paths.entry(directory.clone()).or_default().push(i);
+
+                        // command lines referencing the directory (see `Rule::matches`)
+                        let unix_path = directory.to_string_lossy().trim_end_matches('/').to_lowercase();
+                        if !unix_path.is_empty() {
+                            patterns.push(format!("z:{}\\", unix_path.replace('/', "\\")));
+                            patterns.push(unix_path);
+                            index.pattern_games.extend([i, i]);
+                        }
+                    },
+                    Rule::Substring(_) if !game.matcher().substring_enabled() => (),
+                    Rule::Substring(s) => {
+                        patterns.push(s.to_lowercase());
+                        index.pattern_games.push(i);
+                    },
+                    Rule::CmdRegex(_) => index.unindexed.push(i),
+                }
+            }
+        }
+
+        index.unindexed.dedup();
+        if !patterns.is_empty() {
+            index.automaton = Some(AhoCorasick::new(patterns)?);
+        }
+
+        index.games = games;
+        Ok(index)
+    }
+
+    pub fn games(&self) -> &[Game] {
+        &self.games
+    }
+
+    /// Add the games the process may belong to (positions in `games`) to `candidates`
+    fn add_candidates(&self, p: &ProcessInfo, candidates: &mut BTreeSet<usize>) {
+        if p.is_sandbox_wrapper() {
+            return;
+        }
+
+        candidates.extend(self.unindexed.iter().copied());
+
+        if let Some(automaton) = self.automaton.as_ref() {
+            for haystack in [p.name().to_lowercase(), p.cmd().to_lowercase()] {
+                candidates.extend(automaton.find_overlapping_iter(&haystack)
+                    .map(|found| self.pattern_games[found.pattern().as_usize()]));
+            }
+        }
+
+        let mut lookup = |map: &HashMap<String, Vec<usize>>, key: &str| {
+            if let Some(games) = map.get(key) {
+                candidates.extend(games.iter().copied());
+            }
+        };
+
+        if let Some(app_id) = p.steam_app_id() {
+            lookup(&self.app_ids, app_id);
+        }
+
+        let exe_name = p.exe().and_then(Path::file_name).map(|name| name.to_string_lossy());
+        let arg0 = p.args().first().and_then(|arg0| arg0.rsplit(['/', '\\']).next());
+        for name in [Some(p.name()), exe_name.as_deref(), arg0].into_iter().flatten() {
+            lookup(&self.process_names, name);
+        }
+
+        let mut lookup = |map: &HashMap<PathBuf, Vec<usize>>, key: &Path| {
+            if let Some(games) = map.get(key) {
+                candidates.extend(games.iter().copied());
+            }
+        };
+
+        for path in [p.exe(), p.cwd()].into_iter().flatten() {
+            path.ancestors().for_each(|ancestor| lookup(&self.paths, ancestor));
+        }
+    }
+
+    /// Every game running in the tree, with its topmost matching process (its children belong
+    /// to the game) and the rule it matched. Exact rules are tried on the whole tree before
+    /// substrings, and processes containing a game (launchers, desktop sessions) are never
+    /// matched by substrings.
+    pub fn find_all<'a>(&self, tree: &'a ProcessTree) -> Vec<(&Game, &'a ProcessInfo, &Rule)> {
+        let mut found: Vec<(&Game, &'a ProcessInfo, &Rule)> = vec![];
+        let mut containing: HashSet<Pid> = HashSet::new();
+
+        for precision in [Precision::Exact, Precision::Substring] {
+            let mut stack: Vec<&ProcessInfo> = tree.roots().collect();
+            stack.reverse();
+
+            while let Some(process) = stack.pop() {
+                if found.iter().any(|(_, game_process, _)| game_process.pid() == process.pid()) {
+                    continue;
+                }
+
+                if !containing.contains(&process.pid())
+                    && let Some((game, rule)) = self.matching_rule(process, precision) {
+                    found.push((game, process, rule));
+                    continue;
+                }
+
+                stack.extend(tree.children(process.pid()).rev());
+            }
+
+            for (_, game_process, _) in found.iter() {
+                let mut parent = tree.parent(game_process.pid());
+                while let Some(p) = parent && containing.insert(p.pid()) {
+                    parent = tree.parent(p.pid());
+                }
+            }
+        }
+
+        found
+    }
+
+    /// Game (and rule) matching this process - unlike `find_all`, children aren't searched
+    pub fn matching_game(&self, p: &ProcessInfo) -> Option<(&Game, &Rule)> {
+        [Precision::Exact, Precision::Substring].into_iter()
+            .find_map(|precision| self.matching_rule(p, precision))
+    }
+
+    /// First game (in priority order) matching this process with a rule of the requested precision
+    fn matching_rule(&self, p: &ProcessInfo, precision: Precision) -> Option<(&Game, &Rule)> {
+        let mut candidates = BTreeSet::new();
+        self.add_candidates(p, &mut candidates);
+
+        candidates.iter()
+            .map(|i| &self.games[*i])
+            .find_map(|game| game.matcher().matching_rule(p, precision).map(|rule| (game, rule)))
+    }
+}
+
+#[cfg(test)]
+mod game_index_tests {
+    use super::*;
+
+    fn index() -> GameIndex {
+        GameIndex::new(vec![
+            Game::from_name("Minecraft").with_executable(PathBuf::from("minecraft-launcher")),
+            Game::installed_in(Some("620"), "Portal 2", Path::new("/games/common/Portal 2")),
+            Game::installed_in(Some("400"), "Portal", Path::new("/games/common/Portal")),
+            Game::from_name("Celeste"),
+        ]).expect("no errors!")
+    }
+
+    #[test]
+    fn test_candidates() {
+        let index = index();
+        let mut candidates = BTreeSet::new();
+
+        let proton = ProcessInfo::fake(10, "wine64-preload", &["wine64", r"Z:\games\common\PORTAL\portal.exe"]);
+        index.add_candidates(&proton, &mut candidates);
+        assert_eq!(candidates, BTreeSet::from([2]));
+
+        candidates.clear();
+        let browser = ProcessInfo::fake(11, "firefox", &["firefox", "https://celeste.game"]);
+        index.add_candidates(&browser, &mut candidates);
+        assert_eq!(candidates, BTreeSet::from([3]));
+
+        candidates.clear();
+        let shell = ProcessInfo::fake(12, "bash", &["bash"]);
+        index.add_candidates(&shell, &mut candidates);
+        assert!(candidates.is_empty());
+    }
+
+    #[test]
+    fn test_find_all() {
+        let index = index();
+        let session = Some(Pid::from_u32(5));
+        let tree = ProcessTree::build(vec![
+            (ProcessInfo::fake(5, "systemd", &["/usr/lib/systemd/systemd", "--user"]), Some(Pid::from_u32(1))),
+            (ProcessInfo::fake(10, "steam", &["steam"]), session),
+            (ProcessInfo::fake(11, "reaper", &["reaper", "SteamLaunch", "--", "/games/common/Portal 2/portal2.sh"]), Some(Pid::from_u32(10))),
+            (ProcessInfo::fake(12, "portal2_linux", &["/games/common/Portal 2/portal2_linux"]), Some(Pid::from_u32(11))),
+            (ProcessInfo::fake(20, "minecraft-launcher", &["/usr/bin/minecraft-launcher"]), session),
+            (ProcessInfo::fake(30, "Celeste", &["/opt/Celeste/Celeste"]), session),
+            (ProcessInfo::fake(50, "lutris", &["lutris", "lutris:rungame/Celeste"]), session),
+            (ProcessInfo::fake(51, "hl2_linux", &["/games/common/Portal/hl2_linux"]), Some(Pid::from_u32(50))),
+        ]);
+
+        // every game of the desktop session is found once, with its topmost process
+        // (the launcher contains Portal : its command line doesn't make it Celeste)
+        let found: Vec<(&str, u32)> = index.find_all(&tree).into_iter()
+            .map(|(game, process, _)| (game.name(), process.pid().as_u32()))
+            .collect();
+        assert_eq!(found, vec![("Portal 2", 11), ("Minecraft", 20), ("Portal", 51), ("Celeste", 30)]);
+
+        let rules: Vec<bool> = index.find_all(&tree).into_iter()
+            .map(|(_, _, rule)| matches!(rule, Rule::Substring(_)))
+            .collect();
+        assert_eq!(rules, vec![false, false, false, true]);
+
+        let (game, rule) = index.matching_game(tree.get(Pid::from_u32(30)).unwrap()).expect("substring match");
+        assert_eq!(game.name(), "Celeste");
+        assert!(matches!(rule, Rule::Substring(_)));
+    }
+}
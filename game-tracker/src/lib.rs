@@ -0,0 +1,24 @@
+//! Game tracker : finds installed games, tracks the time spent playing them and enforces
+//! gaming sessions. The binary (`main.rs`) wires these modules together.
+
+pub mod clock;
+pub mod config;
+pub mod process_source;
+pub mod process_tree;
+pub mod tracker;
+pub mod time;
+pub mod scheduler;
+pub mod db;
+pub mod desktop_entry;
+pub mod diagnostics;
+pub mod errors;
+pub mod game_index;
+pub mod matchers;
+pub mod notifier;
+pub mod recording;
+pub mod session;
+pub mod subtasks;
+pub mod vdf;
+
+#[cfg(test)]
+mod test_support;
@@ -1,111 +1,311 @@
-mod process_tree;
-mod tracker;
-mod time;
-mod scheduler;
-mod db;
-mod errors;
-mod session;
-mod subtasks;
-
+use std::path::{Path, PathBuf};
 use std::time::Duration;
-use clap::Parser;
-use crate::db::SaveStatistics;
-use crate::errors::Error;
-use crate::scheduler::GameTrackerScheduler;
-use crate::session::DailyGamingSession;
-use crate::subtasks::{
-    ClockTampering, GamesLogger, RampageMode,
+use clap::{Parser, Subcommand};
+use game_tracker::clock::{SharedClock, SystemClock};
+use game_tracker::config::{Config, ConfigSource};
+use game_tracker::db::SaveStatistics;
+use game_tracker::diagnostics::{self, ProcessFilter};
+use game_tracker::errors::Error;
+use game_tracker::recording::{RecordingSource, ReplaySource};
+use game_tracker::scheduler::GameTrackerScheduler;
+use game_tracker::session::{AllowedHours, BudgetPeriod, DailyGamingSession, Schedule};
+use game_tracker::subtasks::{
+    ClockTampering, ConfigReloader, CurfewGameKiller, GameLimitKiller, GamesLogger, RampageMode,
     SessionEndGameKiller, WarnSessionEnding};
-use crate::time::{format_duration, DurationParser};
-use crate::tracker::GamingTracker;
+use game_tracker::time::{format_duration, DurationParser, WeekStart};
+use game_tracker::tracker::GamingTracker;
 
+/// Command line arguments. Every argument (except --config) can also be set with a
+/// GAME_TRACKER_* environment variable or in the configuration file (in this order of priority)
 #[derive(Parser, Debug, PartialOrd, PartialEq)]
 struct Arguments {
 
-    /// Session duration (ex.: "30h 20m 10s", "3:30:00", "30h 2h 30m 6s 6s")
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Configuration file (default: $XDG_CONFIG_HOME/game-tracker/config.toml,
+    /// /etc/game-tracker/config.toml or built-in configuration)
     #[arg(long)]
+    config: Option<PathBuf>,
+
+    /// Session duration (ex.: "30h 20m 10s", "3:30:00", "30h 2h 30m 6s 6s")
+    #[arg(long, env = "GAME_TRACKER_SESSION_DURATION")]
     session_duration: Option<DurationParser>,
 
-    /// Delay between process scans
-    #[arg(long, default_value_t = 15)]
-    scan_interval: u64,
+    /// Time that can be played each week (same format as --session-duration)
+    #[arg(long, env = "GAME_TRACKER_WEEKLY_DURATION")]
+    weekly_duration: Option<DurationParser>,
+
+    /// Time that can be played each month (same format as --session-duration)
+    #[arg(long, env = "GAME_TRACKER_MONTHLY_DURATION")]
+    monthly_duration: Option<DurationParser>,
+
+    /// First day of the week for --weekly-duration (ex.: "monday", "sun") [default: monday]
+    #[arg(long, env = "GAME_TRACKER_WEEK_START")]
+    week_start: Option<WeekStart>,
+
+    /// Delay between process scans [default: 15]
+    #[arg(long, env = "GAME_TRACKER_SCAN_INTERVAL")]
+    scan_interval: Option<u64>,
+
+    /// Delay between installed games scans (0 disables rescans) [default: 300]
+    #[arg(long, env = "GAME_TRACKER_RESCAN_INTERVAL")]
+    rescan_interval: Option<u64>,
 
     /// Send warning of imminent session end
-    #[arg(long, default_value_t = false)]
-    warn: bool,
+    #[arg(long, env = "GAME_TRACKER_WARN", num_args = 0..=1, default_missing_value = "true")]
+    warn: Option<bool>,
 
     /// Percentage of session played before sending warning
-    /// (value must be between 0 and 100 - you can use decimals)
-    #[arg(long, value_parser = f64_value_parser)]
+    /// (value must be between 0 and 100 - you can use decimals) [default: 90]
+    #[arg(long, env = "GAME_TRACKER_WARNING_THRESHOLD", value_parser = f64_value_parser)]
     warning_threshold: Option<f64>,
 
     /// Monitor games only
-    #[arg(long, default_value_t = false)]
-    monitor_only: bool,
+    #[arg(long, env = "GAME_TRACKER_MONITOR_ONLY", num_args = 0..=1, default_missing_value = "true")]
+    monitor_only: Option<bool>,
 
     /// Enable rampage mode
     /// (kills all games when detected tampering detected)
-    #[arg(long, default_value_t = false)]
-    rampage_mode: bool
+    #[arg(long, env = "GAME_TRACKER_RAMPAGE_MODE", num_args = 0..=1, default_missing_value = "true")]
+    rampage_mode: Option<bool>,
+
+    /// Save every process scan to this file (see the replay command)
+    #[arg(long, env = "GAME_TRACKER_RECORD")]
+    record: Option<PathBuf>,
 }
 
-fn f64_value_parser(v: &str) -> Result<f64, Error> {
-    let parsed = v.parse::<f64>()?;
-    if (0.0..=100.0).contains(&parsed) {
-        Ok(parsed)
+/// Diagnostic commands (the tracker runs when no command is given)
+#[derive(Subcommand, Debug, PartialOrd, PartialEq)]
+enum Command {
+
+    /// Validate the configuration file and print the games found with their matchers
+    CheckConfig,
+
+    /// Print the running processes as a tree, marking the processes counted as games (and why)
+    ListProcesses {
+
+        /// Only show processes of this user (name or id)
+        #[arg(long)]
+        user: Option<String>,
+
+        /// Only show processes whose name or command line contains this text
+        #[arg(long)]
+        name: Option<String>,
+
+        /// Only show this process and its children
+        #[arg(long)]
+        pid: Option<u32>,
+    },
+
+    /// Play a recording (see --record) back through the tracker, with simulated time.
+    /// Nothing is killed and statistics aren't saved.
+    Replay {
+
+        /// Recording file
+        file: PathBuf,
+    },
+}
+
+/// Settings used by the tracker (arguments > environment variables > configuration file > defaults)
+#[derive(Debug)]
+struct Settings {
+    session_duration: Option<DurationParser>,
+    weekly_duration: Option<DurationParser>,
+    monthly_duration: Option<DurationParser>,
+    week_start: WeekStart,
+    schedule: Schedule,
+    allowed_hours: AllowedHours,
+    scan_interval: u64,
+    rescan_interval: u64,
+    warn: bool,
+    warning_threshold: f64,
+    monitor_only: bool,
+    rampage_mode: bool,
+    record: Option<PathBuf>,
+}
+
+impl Settings {
+    fn from(args: &Arguments, config: &Config) -> Result<Self, Error> {
+        let warning_threshold = args.warning_threshold
+            .or(config.session.warning_threshold)
+            .unwrap_or(90.0);
+
+        Ok(Settings {
+            session_duration: args.session_duration.clone().or(config.session.duration.clone()),
+            weekly_duration: args.weekly_duration.clone().or(config.session.weekly_duration.clone()),
+            monthly_duration: args.monthly_duration.clone().or(config.session.monthly_duration.clone()),
+            week_start: args.week_start.or(config.session.week_start).unwrap_or_default(),
+            schedule: config.session.schedule.clone().unwrap_or_default(),
+            allowed_hours: config.session.allowed_hours.clone().unwrap_or_default(),
+            scan_interval: args.scan_interval.or(config.tracker.scan_interval).unwrap_or(15),
+            rescan_interval: args.rescan_interval.or(config.tracker.rescan_interval).unwrap_or(300),
+            warn: args.warn.or(config.session.warn).unwrap_or(false),
+            warning_threshold: validate_threshold(warning_threshold)?,
+            monitor_only: args.monitor_only.or(config.tracker.monitor_only).unwrap_or(false),
+            rampage_mode: args.rampage_mode.or(config.tracker.rampage_mode).unwrap_or(false),
+            record: args.record.clone().or(config.tracker.record.clone()),
+        })
+    }
+}
+
+fn validate_threshold(threshold: f64) -> Result<f64, Error> {
+    if (0.0..=100.0).contains(&threshold) {
+        Ok(threshold)
     } else {
         Err(Error::InvalidThresholdError)
     }
 }
 
+fn f64_value_parser(v: &str) -> Result<f64, Error> {
+    validate_threshold(v.parse::<f64>()?)
+}
+
 
 fn main() -> Result<(), Error> {
     let args = Arguments::parse();
+    let config_source = ConfigSource::locate(args.config.as_deref())
+        .inspect_err(|e| println!("{}", e))?;
+
+    match args.command {
+        Some(Command::CheckConfig) => {
+            if !diagnostics::check_config(&config_source)? {
+                std::process::exit(1);
+            }
+            return Ok(());
+        },
+        Some(Command::ListProcesses { ref user, ref name, pid }) => {
+            let filter = ProcessFilter { user: user.clone(), name: name.clone(), pid };
+            return diagnostics::list_processes(&config_source, &filter);
+        },
+        Some(Command::Replay { ref file }) => return replay(&args, config_source, file),
+        None => (),
+    }
+
+    println!("Loading configuration from {}", config_source);
+
+    let config = Config::load(&config_source)?;
+    let settings = Settings::from(&args, &config)?;
+    let clock = SystemClock::shared();
+
+    let mut source = config.tracker.process_source.unwrap_or_default().create(clock.clone());
+    if let Some(path) = settings.record.as_deref() {
+        println!("Recording process scans to {}", path.display());
+        source = Box::new(RecordingSource::create(path, source, clock.clone())?);
+    }
+
+    let mut tracker = GamingTracker::with_source(source, clock.clone());
+    tracker.load_config(config)?;
+
+    let mut scheduler = create_scheduler(&settings, config_source, tracker, clock)?;
+
+    // today's session (and the budgets of the week and month) continue after a restart
+    let statistics = SaveStatistics::new()?;
+    statistics.restore(scheduler.tracker_mut())?;
+    if scheduler.tracker().session().is_some() {
+        println!("Time already played today : {}", format_duration(&scheduler.tracker().total_time_played()));
+    }
+    scheduler.add(statistics);
+
+    run(&mut scheduler, &settings)
+}
+
+/// Play a recording back with every SubTask (except statistics), as fast as possible
+fn replay(args: &Arguments, config_source: ConfigSource, file: &Path) -> Result<(), Error> {
+    println!("Replaying {} with configuration from {}", file.display(), config_source);
+
+    let config = Config::load(&config_source)?;
+    let settings = Settings::from(args, &config)?;
+    let source = ReplaySource::open(file)?;
+    let clock = source.clock();
+
+    let mut tracker = GamingTracker::with_source(Box::new(source), clock.clone());
+    tracker.load_config(config)?;
+
+    let mut scheduler = create_scheduler(&settings, config_source, tracker, clock)?;
+    run(&mut scheduler, &settings)?;
+
+    println!("End of recording - total time played : {}",
+             format_duration(&scheduler.tracker().total_time_played()));
+    Ok(())
+}
+
+fn create_scheduler(settings: &Settings, config_source: ConfigSource, tracker: GamingTracker,
+                    clock: SharedClock) -> Result<GameTrackerScheduler, Error> {
     let mut scheduler = GameTrackerScheduler::using(
-        Duration::from_secs(args.scan_interval),
-        GamingTracker::try_from("game-tracker/configs/linux.toml")?
+        Duration::from_secs(settings.scan_interval),
+        tracker,
+        clock.clone()
     );
 
     // log games found
     scheduler.add(GamesLogger::new());
-    scheduler.add(ClockTampering::new());
-    scheduler.add(SaveStatistics::new()?);
-
-    // kill games once session reaches it end
-    if let Some(session_duration) = args.session_duration {
-        println!("Session duration enabled - total duration : {}", session_duration.to_string());
-        scheduler.add_gaming_session(
-                DailyGamingSession::from_duration(
-                    session_duration.to_duration()
-                )?
-        );
-
-        if !args.monitor_only {
-            scheduler.add(SessionEndGameKiller::new());
+    scheduler.add(ClockTampering::new(clock.clone()));
+    scheduler.add(ConfigReloader::new(
+        config_source, Duration::from_secs(settings.rescan_interval), clock.clone()
+    ));
+
+    // kill games played longer than their own limit
+    if !settings.monitor_only {
+        scheduler.add(GameLimitKiller::new());
+    }
+
+    // kill games once session reaches it end (any of its budgets)
+    let budgets = [
+        (BudgetPeriod::Daily, settings.session_duration.as_ref()),
+        (BudgetPeriod::Weekly, settings.weekly_duration.as_ref()),
+        (BudgetPeriod::Monthly, settings.monthly_duration.as_ref()),
+    ];
+
+    if budgets.iter().any(|(_, duration)| duration.is_some()) || !settings.schedule.is_empty()
+        || !settings.allowed_hours.is_empty() {
+        let mut session = DailyGamingSession::new(clock)?
+            .with_week_start(settings.week_start.weekday());
+
+        for (period, duration) in budgets {
+            if let Some(duration) = duration {
+                println!("Session budget enabled - {} : {}", period, duration);
+                session = session.with_budget(period, duration.to_duration());
+            }
         }
 
-        // setup warning when session end if near
-        if args.warn {
-            let threshold  = args.warning_threshold.unwrap_or(90.0);
-            let warn_session_ending = WarnSessionEnding::from(
-                threshold, session_duration.to_seconds()
-            );
+        if !settings.schedule.is_empty() {
+            println!("Session schedule enabled - {}", settings.schedule);
+            session = session.with_schedule(settings.schedule.clone());
+        }
 
-            println!("User warning enabled - threshold={}%, warning_after=\"{}\"",
-                     threshold, format_duration(&warn_session_ending.duration())
-            );
+        // warn before each cutoff, notify (and kill) games launched outside the allowed hours
+        if !settings.allowed_hours.is_empty() {
+            println!("Allowed hours enabled - {}", settings.allowed_hours);
+            session = session.with_allowed_hours(settings.allowed_hours.clone());
+            scheduler.add(CurfewGameKiller::new(settings.monitor_only));
+        }
+        scheduler.add_gaming_session(session);
 
-            scheduler.add(warn_session_ending);
+        if !settings.monitor_only {
+            scheduler.add(SessionEndGameKiller::new());
+        }
+
+        // setup warning when session end if near
+        if settings.warn {
+            let threshold = settings.warning_threshold;
+            println!("User warning enabled - threshold={}%", threshold);
+            scheduler.add(WarnSessionEnding::new(threshold));
         }
     }
 
+    Ok(scheduler)
+}
+
+/// Run the scheduler until it stops (end of a replay) or fails
+fn run(scheduler: &mut GameTrackerScheduler, settings: &Settings) -> Result<(), Error> {
     let mut rampage_activated: bool = false;
     loop {
         match scheduler.start() {
             Err(Error::TimeTamperingError(_))
             | Err(Error::TimedExecutionTamperingError(_)) => {
                 println!("Tampering detected - activating rampage mode...");
-                if args.rampage_mode && !rampage_activated {
+                if settings.rampage_mode && !rampage_activated {
                     rampage_activated = true;
                     scheduler.add(RampageMode::new());
                 }
@@ -0,0 +1,320 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use crate::errors::Error;
+use sysinfo::Pid;
+use crate::process_tree::{ProcessInfo, ProcessTree};
+
+/// Matching rules as written in the configuration file. They can be defined for a
+/// whole platform (`[steam.matchers]`) or for a single game (`[steam.game_matchers."Portal 2"]`).
+/// Rules are alternatives : each one must identify the game on its own.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct MatcherConfig {
+
+    /// Exact process name(s)
+    #[serde(default)]
+    process_names: Vec<String>,
+
+    /// Executable path prefix(es)
+    #[serde(default)]
+    exe_prefixes: Vec<PathBuf>,
+
+    /// Regular expression(s) searched in the command line
+    #[serde(default)]
+    cmd_regexes: Vec<String>,
+
+    /// Working directory prefix(es)
+    #[serde(default)]
+    cwd_prefixes: Vec<PathBuf>,
+
+    /// Regular expression(s) searched in the command line and name of a process -
+    /// matching processes are never considered a game (their children still are)
+    #[serde(default)]
+    exclude: Vec<String>,
+
+    /// Search the game's name in command lines (enabled by default, except for games
+    /// recognized by their install directory or executable)
+    #[serde(default)]
+    substring_match: Option<bool>,
+}
+
+impl MatcherConfig {
+
+    /// Compile the configuration into a Matcher (fails on invalid regular expressions)
+    pub fn compile(&self) -> Result<Matcher, Error> {
+        let mut matcher = Matcher::new();
+
+        self.process_names.iter()
+            .for_each(|name| matcher.add(Rule::ProcessName(name.clone())));
+        self.exe_prefixes.iter()
+            .for_each(|prefix| matcher.add(Rule::ExePrefix(prefix.clone())));
+        self.cwd_prefixes.iter()
+            .for_each(|prefix| matcher.add(Rule::CwdPrefix(prefix.clone())));
+
+        for regex in self.cmd_regexes.iter() {
+            matcher.add(Rule::CmdRegex(Regex::new(regex)?));
+        }
+
+        for regex in self.exclude.iter() {
+            matcher.exclude.push(Regex::new(regex)?);
+        }
+
+        matcher.substring_match = self.substring_match;
+        Ok(matcher)
+    }
+}
+
+/// Exact rules are always tried before substring rules (on every game)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Precision {
+    Exact,
+    Substring,
+}
+
+/// Rule used to recognize a game's process
+#[derive(Debug, Clone)]
+pub enum Rule {
+
+    /// Process launched by Steam for this app id (SteamAppId environment variable)
+    SteamAppId(String),
+
+    /// Process executable, working directory or command line is inside the directory
+    InstallDir(PathBuf),
+
+    /// Process executable is exactly this file
+    Executable(PathBuf),
+
+    /// Process name (or executable name) is exactly this name
+    ProcessName(String),
+
+    /// Process executable is inside the directory
+    ExePrefix(PathBuf),
+
+    /// Process working directory is inside the directory
+    CwdPrefix(PathBuf),
+
+    /// Regular expression found in the command line
+    CmdRegex(Regex),
+
+    /// Text found in the command line or name of the process
+    Substring(String),
+}
+
+/// Checks if the command line references a file inside `directory`
+/// (Wine/Proton command lines use Windows paths : Z:\home\...)
+fn cmd_references(p: &ProcessInfo, directory: &Path) -> bool {
+    let unix_path = directory.to_string_lossy();
+    let unix_path = unix_path.trim_end_matches('/');
+    if unix_path.is_empty() {
+        return false;
+    }
+
+    let windows_path = format!("Z:{}\\", unix_path.replace('/', "\\"));
+    p.args().iter().any(|arg| {
+        arg == unix_path
+            || arg.contains(&format!("{}/", unix_path))
+            || arg.to_lowercase().contains(&windows_path.to_lowercase())
+    })
+}
+
+fn file_name_is(path: &Path, name: &str) -> bool {
+    path.file_name().is_some_and(|file_name| file_name == name)
+}
+
+impl Rule {
+
+    pub fn precision(&self) -> Precision {
+        match self {
+            Rule::Substring(_) => Precision::Substring,
+            _ => Precision::Exact,
+        }
+    }
+
+    pub fn matches(&self, p: &ProcessInfo) -> bool {
+        match self {
+            Rule::SteamAppId(app_id) => p.steam_app_id() == Some(app_id.as_str()),
+            Rule::InstallDir(directory) => p.is_running_from(directory) || cmd_references(p, directory),
+            Rule::Executable(executable) => p.exe() == Some(executable.as_path()),
+            Rule::ProcessName(name) => p.name() == name
+                || p.exe().is_some_and(|exe| file_name_is(exe, name))
+                || p.args().first().is_some_and(|arg0| arg0.rsplit(['/', '\\']).next() == Some(name)),
+            Rule::ExePrefix(prefix) => p.exe().is_some_and(|exe| exe.starts_with(prefix)),
+            Rule::CwdPrefix(prefix) => p.cwd().is_some_and(|cwd| cwd.starts_with(prefix)),
+            Rule::CmdRegex(regex) => regex.is_match(&p.cmd()),
+            Rule::Substring(s) => p.cmd_contains(s) || p.name().contains(s.as_str()),
+        }
+    }
+
+    /// Explains why the rule matches the process (only meaningful if it does)
+    pub fn reason(&self, p: &ProcessInfo) -> String {
+        let exe = p.exe().map(Path::display);
+        let cwd = p.cwd().map(Path::display);
+
+        match self {
+            Rule::SteamAppId(_) => format!("launched by Steam with app id {}", p.steam_app_id().unwrap_or("?")),
+            Rule::InstallDir(directory) => match (exe, cwd) {
+                (Some(exe), _) if p.exe().is_some_and(|e| e.starts_with(directory)) => format!("executable is {}", exe),
+                (_, Some(cwd)) if p.cwd().is_some_and(|c| c.starts_with(directory)) => format!("working directory is {}", cwd),
+                _ => "command line references the directory".to_string(),
+            },
+            Rule::Executable(_) | Rule::ExePrefix(_) => format!("executable is {}", exe.map(|e| e.to_string()).unwrap_or_default()),
+            Rule::CwdPrefix(_) => format!("working directory is {}", cwd.map(|c| c.to_string()).unwrap_or_default()),
+            Rule::ProcessName(name) if p.name() == name => "process name".to_string(),
+            Rule::ProcessName(_) => "executable name".to_string(),
+            Rule::CmdRegex(regex) => match regex.find(&p.cmd()) {
+                Some(found) => format!("command line contains \"{}\"", found.as_str()),
+                None => "command line".to_string(),
+            },
+            Rule::Substring(s) if p.name().contains(s.as_str()) => "found in process name".to_string(),
+            Rule::Substring(_) => "found in command line".to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Rule::SteamAppId(app_id) => write!(f, "steam app id {}", app_id),
+            Rule::InstallDir(directory) => write!(f, "install directory {:?}", directory),
+            Rule::Executable(executable) => write!(f, "executable {:?}", executable),
+            Rule::ProcessName(name) => write!(f, "process name \"{}\"", name),
+            Rule::ExePrefix(prefix) => write!(f, "executable prefix {:?}", prefix),
+            Rule::CwdPrefix(prefix) => write!(f, "working directory prefix {:?}", prefix),
+            Rule::CmdRegex(regex) => write!(f, "command line regex \"{}\"", regex),
+            Rule::Substring(s) => write!(f, "substring \"{}\"", s),
+        }
+    }
+}
+
+/// Matcher recognizes the processes of a game : a process matches if one of the rules
+/// matches and none of the exclusion patterns do.
+#[derive(Debug, Clone, Default)]
+pub struct Matcher {
+    rules: Vec<Rule>,
+    exclude: Vec<Regex>,
+    substring_match: Option<bool>,
+}
+
+impl Matcher {
+
+    pub fn new() -> Self {
+        Matcher::default()
+    }
+
+    pub fn add(&mut self, rule: Rule) {
+        self.rules.push(rule);
+    }
+
+    pub fn rules(&self) -> &[Rule] {
+        &self.rules
+    }
+
+    /// Checks if substring rules are used (they can be disabled in the configuration)
+    pub fn substring_enabled(&self) -> bool {
+        self.substring_match.unwrap_or(true)
+    }
+
+    /// Use substring rules or not unless the configuration says otherwise (merged matchers
+    /// with a `substring_match` setting override it)
+    pub fn set_substring_default(&mut self, enabled: bool) {
+        self.substring_match = Some(enabled);
+    }
+
+    pub fn has_rules(&self) -> bool {
+        !self.rules.is_empty()
+    }
+
+    /// Add the rules and exclusions of `other` (its substring setting overrides this one)
+    pub fn merge(&mut self, other: &Matcher) {
+        self.rules.extend(other.rules.iter().cloned());
+        self.exclude.extend(other.exclude.iter().cloned());
+        self.substring_match = other.substring_match.or(self.substring_match);
+    }
+
+    pub fn is_excluded(&self, p: &ProcessInfo) -> bool {
+        let cmd = p.cmd();
+        self.exclude.iter()
+            .any(|regex| regex.is_match(&cmd) || regex.is_match(p.name()))
+    }
+
+    /// Rule (of the requested precision) matching this process (children are not searched)
+    pub fn matching_rule(&self, p: &ProcessInfo, precision: Precision) -> Option<&Rule> {
+        if precision == Precision::Substring && !self.substring_enabled() {
+            return None;
+        }
+
+        if self.is_excluded(p) {
+            return None;
+        }
+
+        self.rules.iter()
+            .filter(|rule| rule.precision() == precision)
+            .find(|rule| rule.matches(p))
+    }
+
+    /// Search the process `pid` (and its children) for a process matching a rule of the requested precision
+    pub fn find<'a>(&self, tree: &'a ProcessTree, pid: Pid, precision: Precision) -> Option<(&'a ProcessInfo, &Rule)> {
+        let process = tree.find(pid, |process| self.matching_rule(process, precision).is_some())?;
+        self.matching_rule(process, precision)
+            .map(|rule| (process, rule))
+    }
+}
+
+#[cfg(test)]
+mod matcher_tests {
+    use super::*;
+
+    fn portal() -> Matcher {
+        let mut matcher = Matcher::new();
+        matcher.add(Rule::InstallDir(PathBuf::from("/games/steamapps/common/Portal")));
+        matcher.add(Rule::Substring("Portal".to_string()));
+        matcher
+    }
+
+    #[test]
+    fn test_install_dir_rule() {
+        let matcher = portal();
+        let native = ProcessInfo::fake(10, "hl2_linux", &["/games/steamapps/common/Portal/hl2_linux", "-game", "portal"]);
+        let proton = ProcessInfo::fake(11, "wine64-preload", &["wine64", r"Z:\games\steamapps\common\Portal\portal.exe"]);
+        let other_game = ProcessInfo::fake(12, "portal2", &["/games/steamapps/common/Portal 2/portal2"]);
+
+        assert!(matcher.matching_rule(&native, Precision::Exact).is_some());
+        assert!(matcher.matching_rule(&proton, Precision::Exact).is_some());
+        assert!(matcher.matching_rule(&other_game, Precision::Exact).is_none());
+        assert!(matcher.matching_rule(&other_game, Precision::Substring).is_some());
+    }
+
+    #[test]
+    fn test_configured_rules() {
+        let config: MatcherConfig = toml::from_str(r#"
+            cmd_regexes = ["net\\.minecraft\\.client\\.main\\.Main"]
+            exclude = ["--dry-run"]
+            substring_match = false
+        "#).expect("valid matcher configuration");
+
+        let mut matcher = portal();
+        matcher.merge(&config.compile().expect("valid regexes"));
+
+        let minecraft = ProcessInfo::fake(20, "java", &["/usr/bin/java", "net.minecraft.client.main.Main"]);
+        let dry_run = ProcessInfo::fake(21, "java", &["/usr/bin/java", "net.minecraft.client.main.Main", "--dry-run"]);
+        let browser = ProcessInfo::fake(22, "firefox", &["firefox", "https://wiki/Portal"]);
+        let gradle = ProcessInfo::fake(23, "java", &["/usr/bin/java", "org.gradle.launcher.daemon.bootstrap.GradleDaemon"]);
+
+        assert!(matches!(
+            matcher.matching_rule(&minecraft, Precision::Exact),
+            Some(Rule::CmdRegex(_))
+        ));
+        assert!(matcher.matching_rule(&dry_run, Precision::Exact).is_none());
+        assert!(matcher.matching_rule(&gradle, Precision::Exact).is_none());
+        assert!(matcher.matching_rule(&browser, Precision::Substring).is_none());
+    }
+
+    #[test]
+    fn test_invalid_regex() {
+        let config: MatcherConfig = toml::from_str(r#"cmd_regexes = ["("]"#)
+            .expect("valid matcher configuration");
+
+        config.compile().expect_err("should not work!");
+    }
+}
@@ -0,0 +1,35 @@
+use std::fmt;
+use std::rc::Rc;
+use crate::errors::Error;
+
+/// Notifier shows messages to the player (budget warnings, games killed...)
+/// (tests use their own notifier : they run without a notification daemon)
+pub trait Notifier: fmt::Debug {
+
+    fn notify(&self, msg: &str) -> Result<(), Error>;
+}
+
+/// Notifier shared by every SubTask
+pub type SharedNotifier = Rc<dyn Notifier>;
+
+/// Desktop notifications (sent to the notification daemon of the session)
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DesktopNotifier;
+
+impl DesktopNotifier {
+    pub fn shared() -> SharedNotifier {
+        Rc::new(DesktopNotifier)
+    }
+}
+
+impl Notifier for DesktopNotifier {
+
+    fn notify(&self, msg: &str) -> Result<(), Error> {
+        notify_rust::Notification::new()
+            .summary("WARNING")
+            .body(msg)
+            .show()?;
+
+        Ok(())
+    }
+}
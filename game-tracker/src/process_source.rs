@@ -0,0 +1,326 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde::Deserialize;
+use sysinfo::{Pid, ProcessRefreshKind, ProcessesToUpdate, System, Uid, UpdateKind};
+use crate::clock::{SharedClock, SystemClock};
+use crate::errors::{Error, TimeTampering};
+use crate::process_tree::{steam_app_id_in, ProcessInfo, ProcessTree};
+
+/// Source of the running processes (scanned every `scan_interval`)
+pub trait ProcessSource: fmt::Debug {
+
+    /// Scan the running processes
+    fn refresh(&mut self) -> Result<ProcessTree, Error>;
+
+    /// Kill the process (returns true if the process is gone)
+    fn kill(&self, pid: Pid) -> bool;
+}
+
+/// `process_source` setting of the `[tracker]` section
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ProcessSourceKind {
+
+    /// /proc reader when /proc is available, sysinfo otherwise
+    #[default]
+    Auto,
+    Proc,
+    Sysinfo,
+}
+
+impl ProcessSourceKind {
+
+    pub fn create(self, clock: SharedClock) -> Box<dyn ProcessSource> {
+        match self {
+            ProcessSourceKind::Auto if Path::new(PROC).join("self/stat").exists() => Box::new(ProcSource::new(clock)),
+            ProcessSourceKind::Proc => Box::new(ProcSource::new(clock)),
+            _ => Box::new(SysinfoSource::new()),
+        }
+    }
+}
+
+/// Processes read with sysinfo. Only processes are refreshed (and their command line,
+/// environment and paths are only read for new processes).
+#[derive(Debug)]
+pub struct SysinfoSource {
+    system: System,
+}
+
+impl SysinfoSource {
+
+    pub fn new() -> Self {
+        SysinfoSource { system: System::new() }
+    }
+
+    fn refresh_kind() -> ProcessRefreshKind {
+        ProcessRefreshKind::nothing()
+            .with_cmd(UpdateKind::OnlyIfNotSet)
+            .with_cwd(UpdateKind::OnlyIfNotSet)
+            .with_exe(UpdateKind::OnlyIfNotSet)
+            .with_user(UpdateKind::OnlyIfNotSet)
+            .with_environ(UpdateKind::OnlyIfNotSet)
+    }
+}
+
+impl Default for SysinfoSource {
+    fn default() -> Self {
+        SysinfoSource::new()
+    }
+}
+
+impl ProcessSource for SysinfoSource {
+
+    fn refresh(&mut self) -> Result<ProcessTree, Error> {
+        self.system.refresh_processes_specifics(ProcessesToUpdate::All, true, SysinfoSource::refresh_kind());
+        Ok(ProcessTree::from(self.system.processes()))
+    }
+
+    fn kill(&self, pid: Pid) -> bool {
+        match self.system.process(pid) {
+            Some(p) => p.kill(),
+            None => true
+        }
+    }
+}
+
+const PROC: &str = "/proc";
+
+/// Process read from /proc : its start time (in clock ticks since boot) and name tell if
+/// the pid was reused or the process executed another program since the last scan.
+#[derive(Debug)]
+struct ProcEntry {
+    start_ticks: u64,
+    process: ProcessInfo,
+}
+
+/// Fields of /proc/<pid>/stat used by the tracker
+#[derive(Debug, PartialEq)]
+struct Stat {
+    name: String,
+    parent: Option<Pid>,
+    start_ticks: u64,
+}
+
+impl Stat {
+
+    /// Parse /proc/<pid>/stat (the name is between parentheses and can contain anything)
+    fn parse(content: &str) -> Option<Stat> {
+        let (_, rest) = content.split_once('(')?;
+        let (name, fields) = rest.rsplit_once(')')?;
+        let fields: Vec<&str> = fields.split_whitespace().collect();
+
+        // fields start at the 3rd field (state) : ppid is the 4th, starttime the 22nd
+        let parent = fields.get(1)?.parse::<u32>().ok()?;
+        let start_ticks = fields.get(19)?.parse::<u64>().ok()?;
+
+        Some(Stat {
+            name: name.to_string(),
+            parent: (parent != 0).then(|| Pid::from_u32(parent)),
+            start_ticks,
+        })
+    }
+}
+
+/// Split a NUL separated file (cmdline)
+fn split_nul(content: &[u8]) -> Vec<String> {
+    content.split(|b| *b == 0)
+        .filter(|s| !s.is_empty())
+        .map(|s| String::from_utf8_lossy(s).to_string())
+        .collect()
+}
+
+/// Native Linux process reader. /proc/<pid>/stat is read on every scan, the command line,
+/// environment, paths and owner only when a process appears (they are kept between scans).
+#[derive(Debug)]
+pub struct ProcSource {
+    clock: SharedClock,
+    root: PathBuf,
+    boot_time: u64,
+    ticks_per_second: u64,
+    processes: HashMap<Pid, ProcEntry>,
+}
+
+impl ProcSource {
+
+    pub fn new(clock: SharedClock) -> Self {
+        // SAFETY: sysconf has no preconditions
+        let ticks = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+        ProcSource::at(Path::new(PROC), if ticks > 0 { ticks as u64 } else { 100 }, clock)
+    }
+
+    /// Reader of a /proc directory mounted at `root` (run times are computed with `clock`)
+    pub fn at(root: &Path, ticks_per_second: u64, clock: SharedClock) -> Self {
+        let boot_time = fs::read_to_string(root.join("stat")).ok()
+            .and_then(|stat| stat.lines()
+                .find_map(|line| line.strip_prefix("btime "))
+                .and_then(|btime| btime.trim().parse().ok()))
+            .unwrap_or(0);
+
+        ProcSource {
+            clock,
+            root: root.to_path_buf(),
+            boot_time,
+            ticks_per_second,
+            processes: HashMap::new(),
+        }
+    }
+
+    /// Read the information that doesn't change during the life of a process
+    fn read_process(&self, pid: Pid, stat: &Stat) -> ProcessInfo {
+        let dir = self.root.join(pid.to_string());
+        let user_id = fs::read_to_string(dir.join("status")).ok()
+            .and_then(|status| status.lines()
+                .find_map(|line| line.strip_prefix("Uid:"))
+                .and_then(|ids| ids.split_whitespace().next())
+                .and_then(|uid| uid.parse::<Uid>().ok()));
+
+        ProcessInfo::new(
+            pid,
+            stat.name.clone(),
+            fs::read(dir.join("cmdline")).map(|c| split_nul(&c)).unwrap_or_default(),
+            fs::read(dir.join("environ")).ok().and_then(|e| steam_app_id_in(
+                e.split(|b| *b == 0).map(String::from_utf8_lossy)
+            )),
+            fs::read_link(dir.join("exe")).ok(),
+            fs::read_link(dir.join("cwd")).ok(),
+            user_id,
+        )
+    }
+}
+
+impl Default for ProcSource {
+    fn default() -> Self {
+        ProcSource::new(SystemClock::shared())
+    }
+}
+
+impl ProcessSource for ProcSource {
+
+    fn refresh(&mut self) -> Result<ProcessTree, Error> {
+        let now = u64::try_from(self.clock.now().timestamp()).map_err(|_| TimeTampering::ClockTamperingError)?;
+        let mut processes = vec![];
+        let mut entries = HashMap::with_capacity(self.processes.len());
+
+        for entry in fs::read_dir(&self.root)?.flatten() {
+            let Some(pid) = entry.file_name().to_str().and_then(|name| name.parse::<u32>().ok()) else {
+                continue;
+            };
+
+            // the process may have exited since the directory was listed
+            let Some(stat) = fs::read_to_string(entry.path().join("stat")).ok().and_then(|s| Stat::parse(&s)) else {
+                continue;
+            };
+
+            let pid = Pid::from_u32(pid);
+            let mut process = match self.processes.remove(&pid) {
+                Some(known) if known.start_ticks == stat.start_ticks && known.process.name() == stat.name => known.process,
+                _ => self.read_process(pid, &stat),
+            };
+
+            let start_time = self.boot_time + stat.start_ticks / self.ticks_per_second;
+            process.set_times(start_time, now.saturating_sub(start_time));
+
+            processes.push((process.clone(), stat.parent));
+            entries.insert(pid, ProcEntry { start_ticks: stat.start_ticks, process });
+        }
+
+        self.processes = entries;
+        Ok(ProcessTree::build(processes))
+    }
+
+    fn kill(&self, pid: Pid) -> bool {
+        // SAFETY: kill has no memory safety preconditions
+        let killed = unsafe { libc::kill(pid.as_u32() as libc::pid_t, libc::SIGKILL) } == 0;
+        killed || std::io::Error::last_os_error().raw_os_error() == Some(libc::ESRCH)
+    }
+}
+
+#[cfg(test)]
+mod process_source_tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use crate::test_support::TempDir;
+    use chrono::{Local, TimeZone};
+    use std::time::{Duration, Instant};
+
+    fn write_process(root: &Path, pid: u32, stat: &str, cmdline: &[&str]) {
+        let dir = root.join(pid.to_string());
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("stat"), stat).unwrap();
+        fs::write(dir.join("cmdline"), cmdline.join("\0")).unwrap();
+        fs::write(dir.join("status"), "Name:\tgame\nUid:\t1000\t1000\t1000\t1000\n").unwrap();
+    }
+
+    fn stat(pid: u32, name: &str, parent: u32, start_ticks: u64) -> String {
+        format!("{} ({}) S {} {} 0 0 -1 4194560 0 0 0 0 0 0 0 0 20 0 1 0 {} 0 0", pid, name, parent, pid, start_ticks)
+    }
+
+    #[test]
+    fn test_parse_stat() {
+        let parsed = Stat::parse(&stat(42, "Web Content (x) ", 1, 4200)).expect("valid stat");
+        assert_eq!(parsed, Stat { name: "Web Content (x) ".to_string(), parent: Some(Pid::from_u32(1)), start_ticks: 4200 });
+
+        let init = Stat::parse(&stat(1, "systemd", 0, 1)).expect("valid stat");
+        assert_eq!(init.parent, None);
+
+        assert_eq!(Stat::parse("42 (truncated"), None);
+    }
+
+    #[test]
+    fn test_incremental_refresh() {
+        let directory = TempDir::new("proc");
+        let root = directory.path();
+        fs::write(root.join("stat"), "cpu 0 0 0 0\nbtime 1700000000\n").unwrap();
+        write_process(root, 10, &stat(10, "steam", 1, 500), &["/usr/bin/steam"]);
+        write_process(root, 11, &stat(11, "portal2_linux", 10, 1000), &["/games/Portal 2/portal2_linux", "-game", "portal2"]);
+        fs::write(root.join("11/environ"), "HOME=/home/user\0SteamGameId=0\0SteamAppId=620\0LANG=C\0").unwrap();
+
+        let clock = ManualClock::new(Local.timestamp_opt(1700000100, 0).unwrap());
+        let mut source = ProcSource::at(root, 100, clock.clone());
+        let tree = source.refresh().expect("no errors!");
+        let game = tree.get(Pid::from_u32(11)).expect("game is running");
+        assert_eq!(game.args(), ["/games/Portal 2/portal2_linux", "-game", "portal2"]);
+        assert_eq!(game.start_time(), 1700000010);
+        assert_eq!(game.run_time(), 90);
+        assert_eq!(game.user_id(), "1000".parse::<Uid>().ok().as_ref());
+        assert_eq!(game.steam_app_id(), Some("620"));
+        assert_eq!(tree.get(Pid::from_u32(10)).and_then(ProcessInfo::steam_app_id), None);
+        assert_eq!(tree.parent(Pid::from_u32(11)).map(ProcessInfo::name), Some("steam"));
+
+        // command lines are only read for new processes
+        write_process(root, 11, &stat(11, "portal2_linux", 10, 1000), &["changed"]);
+        clock.advance(Duration::from_secs(60));
+        let tree = source.refresh().expect("no errors!");
+        assert_eq!(tree.get(Pid::from_u32(11)).unwrap().cmd(), "/games/Portal 2/portal2_linux -game portal2");
+        assert_eq!(tree.get(Pid::from_u32(11)).unwrap().run_time(), 150);
+
+        // the pid was reused by another process
+        write_process(root, 11, &stat(11, "bash", 10, 9000), &["bash"]);
+        fs::remove_dir_all(root.join("10")).unwrap();
+        let tree = source.refresh().expect("no errors!");
+        assert_eq!(tree.get(Pid::from_u32(11)).unwrap().cmd(), "bash");
+        assert_eq!(tree.roots().map(ProcessInfo::pid).collect::<Vec<_>>(), vec![Pid::from_u32(11)]);
+    }
+
+    /// Compare the cost of a scan of the running processes (run with `cargo test --release -- --ignored`)
+    #[test]
+    #[ignore]
+    fn bench_process_sources() {
+        let runs = 20;
+        let sources: Vec<(&str, Box<dyn ProcessSource>)> = vec![
+            ("sysinfo", Box::new(SysinfoSource::new())),
+            ("/proc", Box::new(ProcSource::default())),
+        ];
+
+        for (name, mut source) in sources {
+            source.refresh().expect("no errors!");
+            let start = Instant::now();
+            for _ in 0..runs {
+                source.refresh().expect("no errors!");
+            }
+            println!("{} : {}us per scan (average of {} runs)", name, start.elapsed().as_micros() / runs, runs);
+        }
+    }
+}
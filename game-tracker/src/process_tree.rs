@@ -1,27 +1,47 @@
-use std::collections::{BTreeMap, HashMap};
-use std::collections::btree_map::{Iter, IterMut};
+use std::collections::{HashMap, HashSet};
 use std::hash::{Hash};
-use chrono::{Utc};
-use sysinfo::{Pid, Process};
+use std::path::{Path, PathBuf};
+use sysinfo::{Pid, Process, Uid};
+
+/// Environment variables set by Steam on processes launched for a game
+const STEAM_APP_ID_VARIABLES: [&str; 3] = ["SteamAppId", "SteamGameId", "STEAM_COMPAT_APP_ID"];
+
+/// Processes created by sandboxes (Flatpak, Snap, Steam runtime) to launch the real application
+const SANDBOX_WRAPPERS: [&str; 6] = ["bwrap", "pv-bwrap", "srt-bwrap", "flatpak-spawn", "flatpak", "snap-confine"];
+
+/// Steam app id set in a process environment (`KEY=value` entries). The rest of the
+/// environment is not kept.
+pub fn steam_app_id_in<S: AsRef<str>>(environ: impl IntoIterator<Item = S>) -> Option<String> {
+    let mut app_ids: [Option<String>; STEAM_APP_ID_VARIABLES.len()] = Default::default();
+
+    for var in environ {
+        if let Some((key, value)) = var.as_ref().split_once('=')
+            && let Some(i) = STEAM_APP_ID_VARIABLES.iter().position(|k| *k == key)
+            && !value.is_empty() && value != "0" {
+            app_ids[i] = Some(value.to_string());
+        }
+    }
+
+    app_ids.into_iter().flatten().next()
+}
 
 /// ProcessInfo represents a running process. It is based off sysinfo::Process.
 /// The key differences are the hash functions (that way we can insert them in a BTree
 /// in order to optimize searches/inserts).
 #[derive(Debug, Clone)]
 pub struct ProcessInfo {
-    children: Option<ProcessTree>,
     name: String,
     cmd: Vec<String>,
+    steam_app_id: Option<String>,
+    exe: Option<PathBuf>,
+    cwd: Option<PathBuf>,
+    user_id: Option<Uid>,
     pid: Pid,
     run_time: u64,
     start_time: u64,
 }
 
-impl Eq for ProcessInfo {
-    fn assert_receiver_is_total_eq(&self) {
-        assert_eq!(self, self)
-    }
-}
+impl Eq for ProcessInfo {}
 
 impl PartialEq<Self> for ProcessInfo {
 
@@ -44,24 +64,50 @@ impl ProcessInfo {
 
     pub fn from(proc: &Process) -> ProcessInfo {
         ProcessInfo {
-            children: None,
             name: proc.name().to_string_lossy().to_string(),
             cmd: proc.cmd().iter().map(|s| s.to_string_lossy().to_string()).collect(),
+            steam_app_id: steam_app_id_in(proc.environ().iter().map(|var| var.to_string_lossy())),
+            exe: proc.exe().map(Path::to_path_buf),
+            cwd: proc.cwd().map(Path::to_path_buf),
+            user_id: proc.user_id().cloned(),
             run_time: proc.run_time(),
-            pid: proc.pid().clone(),
+            pid: proc.pid(),
             start_time: proc.start_time()
         }
     }
 
-    fn insert_child(&mut self, pid: Pid, child: ProcessInfo) {
-        match self.children {
-            Some(ref mut proc_tree) => {
-                proc_tree.insert(pid, Box::new(child));
-            }
-            None => {
-                self.children = Some(ProcessTree::new());
-                self.children.as_mut().unwrap().insert(pid, Box::new(child));
-            }
+    /// Process read without sysinfo (run and start times are set with `set_times`)
+    pub fn new(
+        pid: Pid,
+        name: String,
+        cmd: Vec<String>,
+        steam_app_id: Option<String>,
+        exe: Option<PathBuf>,
+        cwd: Option<PathBuf>,
+        user_id: Option<Uid>
+    ) -> ProcessInfo {
+        ProcessInfo { name, cmd, steam_app_id, exe, cwd, user_id, run_time: 0, pid, start_time: 0 }
+    }
+
+    /// Set the start time (seconds since epoch) and run time (in seconds)
+    pub fn set_times(&mut self, start_time: u64, run_time: u64) {
+        self.start_time = start_time;
+        self.run_time = run_time;
+    }
+
+    /// Process without children, environment or paths (used to test matching)
+    #[cfg(test)]
+    pub fn fake(pid: u32, name: &str, cmd: &[&str]) -> ProcessInfo {
+        ProcessInfo {
+            name: name.to_string(),
+            cmd: cmd.iter().map(|s| s.to_string()).collect(),
+            steam_app_id: None,
+            exe: None,
+            cwd: None,
+            user_id: None,
+            run_time: 0,
+            pid: Pid::from_u32(pid),
+            start_time: 0
         }
     }
 
@@ -69,14 +115,16 @@ impl ProcessInfo {
         self.cmd.join(" ")
     }
 
+    pub fn args(&self) -> &[String] {
+        &self.cmd
+    }
+
     pub fn run_time(&self) -> u64 {
         self.run_time
     }
 
     pub fn pid(&self) -> Pid { self.pid }
 
-    pub fn children(&self) -> &Option<ProcessTree> { &self.children }
-
     pub fn cmd_contains(&self, s: &str) -> bool {
         self.cmd().contains(s)
     }
@@ -85,108 +133,322 @@ impl ProcessInfo {
 
     pub fn start_time(&self) -> u64 { self.start_time }
 
-    /// This function searches a string `s` inside a ProcessInfo (cmd and name)
-    /// Goes through the list of children (if they are present)
-    pub fn find(&self, s: &str) -> Option<&ProcessInfo> {
-        if self.cmd_contains(s) || self.name.contains(s) {
-            return Some(self)
-        }
+    pub fn exe(&self) -> Option<&Path> { self.exe.as_deref() }
 
-        match self.children {
-            Some(ref children) => {
-                for (_, n) in children.iter() {
-                    match n.find(s) {
-                        Some(found) => return Some(found),
-                        None => ()
-                    };
-                }
-            }
-            None => ()
-        }
+    pub fn cwd(&self) -> Option<&Path> { self.cwd.as_deref() }
 
-        None
-    }
-
-    #[allow(dead_code)]
-    pub fn to_string(&self, level: usize) -> String {
-        let mut output = String::from(
-            format!("{}|__<{}> {}\n", " ".repeat(level), self.pid, self.cmd())
-        );
+    pub fn user_id(&self) -> Option<&Uid> { self.user_id.as_ref() }
 
-        if let Some(children) = self.children.as_ref() {
-            for (_, v) in children.iter() {
-                output += v.to_string(level+1).as_str();
-            }
-        }
+    /// Checks if the process executable or working directory is inside `directory`
+    pub fn is_running_from(&self, directory: &Path) -> bool {
+        self.exe().is_some_and(|exe| exe.starts_with(directory))
+            || self.cwd().is_some_and(|cwd| cwd.starts_with(directory))
+    }
 
-        output
+    /// Steam app id of the game that launched this process (if it was launched by Steam)
+    pub fn steam_app_id(&self) -> Option<&str> {
+        self.steam_app_id.as_deref()
     }
 
+    /// Checks if the process only launches a sandboxed process (ex.: bwrap, flatpak-spawn)
+    pub fn is_sandbox_wrapper(&self) -> bool {
+        SANDBOX_WRAPPERS.contains(&self.name.as_str())
+    }
 }
 
-/// This class represents a process tree. It's a BTree of ProcessInfo.
-/// This structure is recursive and is basically a wrapper around BTreeMap
-/// to facilitate searching.
+/// Node of the process table : a process and the pids of its parent and children
 #[derive(Debug, Clone)]
+struct Node {
+    process: ProcessInfo,
+    parent: Option<Pid>,
+    children: Vec<Pid>,
+}
+
+/// This class represents a process tree. Processes are stored in a flat table indexed
+/// by pid (parents and children are pids), so lookups are O(1) and walking the tree
+/// never recurses. Roots and children are kept sorted by pid.
+#[derive(Debug, Clone, Default)]
 pub struct ProcessTree {
-    inner: BTreeMap<Pid, Box<ProcessInfo>>
+    nodes: HashMap<Pid, Node>,
+    roots: Vec<Pid>,
 }
 
 impl ProcessTree {
 
     pub fn new() -> ProcessTree {
-        ProcessTree{
-            inner: BTreeMap::new()
-        }
+        ProcessTree::default()
     }
 
-    fn insert_process(&mut self, proc: &Process, parent: Pid) -> bool {
-        if self.contains_key(&parent) {
-            self.inner.get_mut(&parent).unwrap()
-                .insert_child(proc.pid(), ProcessInfo::from(proc));
-            return true;
+    /// Build the tree from processes and their parent, in two passes : processes are first
+    /// indexed by pid, then linked to their parent. Every process appears exactly once,
+    /// whatever the order of the pids (children can have a lower pid than their parent after
+    /// a pid wraparound). Processes without a known parent, or whose parent is init (pid 1),
+    /// are roots.
+    pub fn build(processes: Vec<(ProcessInfo, Option<Pid>)>) -> ProcessTree {
+        // first pass : index processes by pid
+        let mut nodes: HashMap<Pid, Node> = HashMap::with_capacity(processes.len());
+        for (process, parent) in processes {
+            nodes.insert(process.pid, Node { process, parent, children: vec![] });
         }
 
-        for node in self.inner.values_mut() {
-            if let Some(children) = node.children.as_mut() {
-                if children.insert_process(proc, parent) {
-                    return true;
-                }
+        // second pass : link children to their parent
+        let mut pids: Vec<Pid> = nodes.keys().copied().collect();
+        pids.sort();
+
+        let mut roots = vec![];
+        for pid in pids.iter() {
+            let parent = nodes[pid].parent
+                .filter(|parent| parent.as_u32() != 1 && parent != pid && nodes.contains_key(parent));
+
+            match parent {
+                Some(parent) => nodes.get_mut(&parent).unwrap().children.push(*pid),
+                None => roots.push(*pid),
             }
+            nodes.get_mut(pid).unwrap().parent = parent;
         }
 
-        false
-    }
+        let mut tree = ProcessTree { nodes, roots };
 
+        // processes unreachable from a root are part of a parent cycle (only possible with an
+        // inconsistent snapshot) : the lowest unreachable pid becomes a root until none are left
+        let mut reachable: HashSet<Pid> = tree.descendants(tree.roots.iter().copied()).collect();
+        for pid in pids {
+            if !reachable.contains(&pid) {
+                tree.detach(pid);
+                reachable.extend(tree.descendants([pid].into_iter()));
+            }
+        }
+
+        tree.roots.sort();
+        tree
+    }
 
     pub fn from(processes: &HashMap<Pid, Process>) -> ProcessTree {
-        let mut tree: ProcessTree = ProcessTree::new();
-
-        BTreeMap::from_iter(processes.iter()).iter()
-            .for_each(|(pid, process)| {
-                if let Some(parent) = process.parent() && parent.as_u32() != 1 {
-                    tree.insert_process(process, process.parent().unwrap());
-                } else {
-                    tree.inner.insert(
-                        Pid::from_u32(pid.as_u32()),
-                        Box::new(ProcessInfo::from(process))
-                    );
-                }
-            });
+        ProcessTree::build(processes.values()
+            .map(|process| (ProcessInfo::from(process), process.parent()))
+            .collect())
+    }
 
-        tree
+    /// Make `pid` a root (it is removed from the children of its parent)
+    fn detach(&mut self, pid: Pid) {
+        if let Some(parent) = self.nodes.get_mut(&pid).and_then(|node| node.parent.take()) {
+            self.nodes.get_mut(&parent).unwrap().children.retain(|child| *child != pid);
+        }
+        self.roots.push(pid);
+    }
+
+    /// Pids of the processes and all their descendants (depth-first, parents before children)
+    fn descendants<I>(&self, pids: I) -> impl Iterator<Item = Pid>
+    where I: DoubleEndedIterator<Item = Pid> {
+        let mut stack: Vec<Pid> = pids.rev().collect();
+        std::iter::from_fn(move || {
+            let pid = stack.pop()?;
+            stack.extend(self.nodes[&pid].children.iter().rev());
+            Some(pid)
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn get(&self, pid: Pid) -> Option<&ProcessInfo> {
+        self.nodes.get(&pid).map(|node| &node.process)
+    }
+
+    /// Parent of the process (None for roots)
+    pub fn parent(&self, pid: Pid) -> Option<&ProcessInfo> {
+        self.nodes.get(&pid)?.parent.and_then(|parent| self.get(parent))
+    }
+
+    /// Direct children of the process (sorted by pid)
+    pub fn children(&self, pid: Pid) -> impl DoubleEndedIterator<Item = &ProcessInfo> {
+        self.nodes.get(&pid).into_iter()
+            .flat_map(|node| node.children.iter())
+            .map(|child| &self.nodes[child].process)
+    }
+
+    /// Processes without parent (sorted by pid)
+    pub fn roots(&self) -> impl Iterator<Item = &ProcessInfo> {
+        self.roots.iter().map(|pid| &self.nodes[pid].process)
+    }
+
+    /// Every process, parents before their children
+    pub fn iter(&self) -> impl Iterator<Item = &ProcessInfo> {
+        self.descendants(self.roots.iter().copied()).map(|pid| &self.nodes[&pid].process)
+    }
+
+    /// The process `pid` and all its descendants (depth-first, parents before children)
+    pub fn subtree(&self, pid: Pid) -> impl Iterator<Item = &ProcessInfo> {
+        let root = self.nodes.contains_key(&pid).then_some(pid);
+        self.descendants(root.into_iter()).map(|pid| &self.nodes[&pid].process)
+    }
+
+    /// This function searches for a process matching `predicate` in the process `pid` and its
+    /// children (depth-first). Sandbox wrappers are never returned since their command line
+    /// contains the command of the sandboxed process.
+    pub fn find<F>(&self, pid: Pid, predicate: F) -> Option<&ProcessInfo>
+    where F: Fn(&ProcessInfo) -> bool {
+        self.subtree(pid)
+            .find(|process| !process.is_sandbox_wrapper() && predicate(process))
+    }
+
+    /// Text representation of the process and its children (one line per process).
+    /// `describe` returns the text printed for a process - processes for which it returns
+    /// None are skipped (with their children).
+    pub fn to_string<F>(&self, pid: Pid, describe: &F) -> String
+    where F: Fn(&ProcessInfo) -> Option<String> {
+        let mut output = String::new();
+        let mut stack = vec![(pid, 0)];
+
+        while let Some((pid, level)) = stack.pop() {
+            let Some(description) = self.get(pid).and_then(describe) else {
+                continue;
+            };
+
+            output += &format!("{}|__<{}> {}\n", " ".repeat(level), pid, description);
+            stack.extend(self.children(pid).rev().map(|child| (child.pid(), level + 1)));
+        }
+
+        output
+    }
+}
+
+#[cfg(test)]
+mod process_tree_tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use std::time::Instant;
+    use proptest::prelude::*;
+
+    fn fake(pid: u32, parent: Option<u32>) -> (ProcessInfo, Option<Pid>) {
+        (ProcessInfo::fake(pid, "process", &[]), parent.map(Pid::from_u32))
     }
 
-    pub fn iter(&self) -> Iter<'_, Pid, Box<ProcessInfo>> {
-        self.inner.iter()
+    /// Every (pid, parent) found walking the tree, sorted - parents are None for roots
+    fn edges(tree: &ProcessTree) -> Vec<(Pid, Option<Pid>)> {
+        let mut found: Vec<(Pid, Option<Pid>)> = tree.iter()
+            .map(|process| (process.pid(), tree.parent(process.pid()).map(ProcessInfo::pid)))
+            .collect();
+        found.sort();
+        found
     }
 
-    pub fn insert(&mut self, pid: Pid, process: Box<ProcessInfo>) -> Option<Box<ProcessInfo>> {
-        self.inner.insert(pid, process)
+    #[test]
+    fn test_children_with_lower_pids() {
+        // pid wraparound : children were created after their parent but have lower pids
+        let tree = ProcessTree::build(vec![
+            fake(300, Some(200)),
+            fake(200, Some(1)),
+            fake(5, Some(200)),
+            fake(3, Some(5)),
+            fake(1, None),
+        ]);
+
+        let found = edges(&tree);
+
+        assert_eq!(found, vec![
+            (Pid::from_u32(1), None),
+            (Pid::from_u32(3), Some(Pid::from_u32(5))),
+            (Pid::from_u32(5), Some(Pid::from_u32(200))),
+            (Pid::from_u32(200), None),
+            (Pid::from_u32(300), Some(Pid::from_u32(200))),
+        ]);
     }
 
-    pub fn contains_key(&self, pid: &Pid) -> bool {
-        self.inner.contains_key(pid)
+    #[test]
+    fn test_parent_cycles() {
+        let tree = ProcessTree::build(vec![fake(7, Some(8)), fake(8, Some(7)), fake(9, Some(9))]);
+
+        let found = edges(&tree);
+
+        assert_eq!(found, vec![
+            (Pid::from_u32(7), None),
+            (Pid::from_u32(8), Some(Pid::from_u32(7))),
+            (Pid::from_u32(9), None),
+        ]);
     }
 
+    proptest! {
+        #[test]
+        fn every_process_appears_once(parents in prop::collection::btree_map(2u32..200, prop::option::of(0u32..200), 0..150)) {
+            let processes = parents.iter()
+                .map(|(pid, parent)| fake(*pid, *parent))
+                .collect();
+            let tree = ProcessTree::build(processes);
+
+            let found = edges(&tree);
+            prop_assert_eq!(tree.len(), parents.len());
+
+            let pids: Vec<Pid> = found.iter().map(|(pid, _)| *pid).collect();
+            let expected: Vec<Pid> = parents.keys().copied().map(Pid::from_u32).collect();
+            prop_assert_eq!(pids, expected);
+
+            for (pid, parent) in found {
+                let declared = parents[&pid.as_u32()].map(Pid::from_u32);
+                match parent {
+                    // children are always attached to their declared parent
+                    Some(parent) => prop_assert_eq!(Some(parent), declared),
+                    // roots have no parent in the snapshot (or init), unless they break a cycle
+                    None => prop_assert!(
+                        declared.is_none_or(|declared| declared.as_u32() == 1 || !parents.contains_key(&declared.as_u32()))
+                            || in_cycle(&parents, pid.as_u32())
+                    ),
+                }
+            }
+        }
+    }
+
+    /// Checks if following the parents of `pid` leads to a cycle
+    fn in_cycle(parents: &BTreeMap<u32, Option<u32>>, pid: u32) -> bool {
+        let mut seen = HashSet::new();
+        let mut current = pid;
+        while let Some(Some(parent)) = parents.get(&current) {
+            if *parent == 1 || !seen.insert(current) {
+                return *parent != 1;
+            }
+            current = *parent;
+        }
+        false
+    }
+
+    /// Benchmark over a synthetic 10k processes table (run with `cargo test --release -- --ignored`)
+    #[test]
+    #[ignore]
+    fn bench_synthetic_process_table() {
+        // a few deep chains (shells, launchers) and a lot of wide subtrees (browsers, services)
+        let processes = || (2..10_002u32)
+            .map(|pid| {
+                let parent = match pid % 10 {
+                    0 => 1,
+                    1 => pid - 1,
+                    _ => pid - pid % 10,
+                };
+                let process = ProcessInfo::fake(pid, "process", &["/usr/bin/process", "--type=renderer"]);
+                (process, Some(Pid::from_u32(parent)))
+            })
+            .collect::<Vec<_>>();
+
+        let runs = 20;
+        let (mut build, mut find) = (0, 0);
+        for _ in 0..runs {
+            let processes = processes();
+            let start = Instant::now();
+            let tree = ProcessTree::build(processes);
+            build += start.elapsed().as_micros();
+
+            let start = Instant::now();
+            for root in tree.roots() {
+                assert!(tree.find(root.pid(), |p| p.name() == "game").is_none());
+            }
+            find += start.elapsed().as_micros();
+            assert_eq!(tree.iter().count(), 10_000);
+        }
+
+        println!("10k processes : build {}us, full search {}us (average of {} runs)", build / runs, find / runs, runs);
+    }
 }
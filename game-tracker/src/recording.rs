@@ -0,0 +1,279 @@
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Lines, Write};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use sysinfo::{Pid, Uid};
+use crate::clock::{Clock, ManualClock, SharedClock};
+use crate::errors::Error;
+use crate::process_source::ProcessSource;
+use crate::process_tree::{steam_app_id_in, ProcessInfo, ProcessTree};
+
+/// Process as stored in a recording. Only the Steam app id is kept from the environment
+/// (the rest of the environment may contain secrets).
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct RecordedProcess {
+    pid: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    ppid: Option<u32>,
+    name: String,
+    cmd: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    exe: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    cwd: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    env: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    uid: Option<String>,
+    start_time: u64,
+
+    /// Run time when the process was first recorded
+    run_time: u64,
+}
+
+impl RecordedProcess {
+
+    fn from(process: &ProcessInfo, parent: Option<Pid>) -> Self {
+        RecordedProcess {
+            pid: process.pid().as_u32(),
+            ppid: parent.map(|pid| pid.as_u32()),
+            name: process.name().to_string(),
+            cmd: process.args().to_vec(),
+            exe: process.exe().map(Path::to_path_buf),
+            cwd: process.cwd().map(Path::to_path_buf),
+            env: process.steam_app_id()
+                .map(|app_id| format!("SteamAppId={}", app_id))
+                .into_iter()
+                .collect(),
+            uid: process.user_id().map(|uid| uid.to_string()),
+            start_time: process.start_time(),
+            run_time: process.run_time(),
+        }
+    }
+
+    /// Same process, maybe older
+    fn is_same(&self, other: &RecordedProcess) -> bool {
+        RecordedProcess { run_time: other.run_time, ..self.clone() } == *other
+    }
+
+    /// Process as it was `elapsed` seconds after it was recorded
+    fn to_process(&self, elapsed: u64) -> (ProcessInfo, Option<Pid>) {
+        let mut process = ProcessInfo::new(
+            Pid::from_u32(self.pid),
+            self.name.clone(),
+            self.cmd.clone(),
+            steam_app_id_in(&self.env),
+            self.exe.clone(),
+            self.cwd.clone(),
+            self.uid.as_deref().and_then(|uid| uid.parse::<Uid>().ok()),
+        );
+        process.set_times(self.start_time, self.run_time + elapsed);
+
+        (process, self.ppid.map(Pid::from_u32))
+    }
+}
+
+/// One line of a recording : processes started and exited since the previous snapshot
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct Snapshot {
+
+    /// Seconds since epoch
+    time: i64,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    started: Vec<RecordedProcess>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    exited: Vec<u32>,
+}
+
+/// Records every scan of another source to a file (one JSON line per scan). Only the
+/// changes since the previous scan are written, so recordings stay small.
+#[derive(Debug)]
+pub struct RecordingSource {
+    source: Box<dyn ProcessSource>,
+    clock: SharedClock,
+    output: BufWriter<File>,
+    processes: HashMap<Pid, RecordedProcess>,
+}
+
+impl RecordingSource {
+
+    pub fn create(path: &Path, source: Box<dyn ProcessSource>, clock: SharedClock) -> Result<Self, Error> {
+        Ok(RecordingSource {
+            source,
+            clock,
+            output: BufWriter::new(File::create(path)?),
+            processes: HashMap::new(),
+        })
+    }
+
+    fn record(&mut self, tree: &ProcessTree) -> Result<(), Error> {
+        let mut snapshot = Snapshot { time: self.clock.now().timestamp(), ..Snapshot::default() };
+        let mut processes = HashMap::with_capacity(tree.len());
+
+        for process in tree.iter() {
+            let parent = tree.parent(process.pid()).map(ProcessInfo::pid);
+            let recorded = RecordedProcess::from(process, parent);
+
+            // pids are reused : a process with a different start time is a new process
+            match self.processes.remove(&process.pid()) {
+                Some(previous) if previous.is_same(&recorded) => {
+                    processes.insert(process.pid(), previous);
+                    continue;
+                },
+                Some(_) => {
+                    snapshot.exited.push(recorded.pid);
+                    snapshot.started.push(recorded.clone());
+                },
+                None => snapshot.started.push(recorded.clone()),
+            }
+
+            processes.insert(process.pid(), recorded);
+        }
+
+        snapshot.exited.extend(self.processes.keys().map(|pid| pid.as_u32()));
+        snapshot.exited.sort();
+        self.processes = processes;
+
+        serde_json::to_writer(&mut self.output, &snapshot)?;
+        self.output.write_all(b"\n")?;
+
+        // keep the recording usable if the tracker is stopped
+        self.output.flush()?;
+        Ok(())
+    }
+}
+
+impl ProcessSource for RecordingSource {
+
+    fn refresh(&mut self) -> Result<ProcessTree, Error> {
+        let tree = self.source.refresh()?;
+        self.record(&tree)?;
+
+        Ok(tree)
+    }
+
+    fn kill(&self, pid: Pid) -> bool {
+        self.source.kill(pid)
+    }
+}
+
+/// Clock of a replay : time only moves to the time of the snapshots (waiting between two
+/// scans is instant and doesn't move it, the time played comes from the recording only)
+#[derive(Debug)]
+pub struct ReplayClock {
+    time: Rc<ManualClock>,
+}
+
+impl Clock for ReplayClock {
+
+    fn now(&self) -> DateTime<Local> {
+        self.time.now()
+    }
+
+    fn monotonic(&self) -> Instant {
+        self.time.monotonic()
+    }
+
+    fn sleep(&self, _: Duration) {}
+}
+
+/// Plays a recording back : every scan returns the next snapshot and moves the clock
+/// to the time it was recorded. Killed processes disappear from the following snapshots
+/// (nothing is really killed). Scans fail with `EndOfRecordingError` once every snapshot
+/// has been played.
+#[derive(Debug)]
+pub struct ReplaySource {
+    snapshots: Lines<BufReader<File>>,
+    next: Option<Snapshot>,
+    clock: Rc<ReplayClock>,
+    /// Running processes and the time they were recorded
+    processes: BTreeMap<Pid, (RecordedProcess, i64)>,
+    killed: RefCell<Vec<Pid>>,
+}
+
+impl ReplaySource {
+
+    pub fn open(path: &Path) -> Result<Self, Error> {
+        let mut snapshots = BufReader::new(File::open(path)?).lines();
+        let next = ReplaySource::read_snapshot(&mut snapshots)?;
+        let start = next.as_ref().map(|snapshot| snapshot.time).unwrap_or_default();
+
+        Ok(ReplaySource {
+            snapshots,
+            next,
+            clock: Rc::new(ReplayClock { time: ManualClock::new(ReplaySource::local_time(start)?) }),
+            processes: BTreeMap::new(),
+            killed: RefCell::new(vec![]),
+        })
+    }
+
+    /// Clock of the recording (starts at the time of the first snapshot)
+    pub fn clock(&self) -> Rc<ReplayClock> {
+        self.clock.clone()
+    }
+
+    fn read_snapshot(snapshots: &mut Lines<BufReader<File>>) -> Result<Option<Snapshot>, Error> {
+        for line in snapshots.by_ref() {
+            let line = line?;
+            if !line.trim().is_empty() {
+                return Ok(Some(serde_json::from_str(&line)?));
+            }
+        }
+
+        Ok(None)
+    }
+
+    fn local_time(time: i64) -> Result<DateTime<Local>, Error> {
+        DateTime::from_timestamp(time, 0)
+            .map(|time| time.with_timezone(&Local))
+            .ok_or(Error::RecordingError(format!("invalid snapshot time {}", time)))
+    }
+
+    /// Move the clock to `time` (going back only moves the wall clock, like a clock change)
+    fn set_time(&self, time: DateTime<Local>) {
+        match time.signed_duration_since(self.clock.now()).to_std() {
+            Ok(elapsed) => self.clock.time.advance(elapsed),
+            Err(_) => self.clock.time.set_now(time),
+        }
+    }
+}
+
+impl ProcessSource for ReplaySource {
+
+    fn refresh(&mut self) -> Result<ProcessTree, Error> {
+        let Some(snapshot) = self.next.take() else {
+            return Err(Error::EndOfRecordingError);
+        };
+        self.next = ReplaySource::read_snapshot(&mut self.snapshots)?;
+        self.set_time(ReplaySource::local_time(snapshot.time)?);
+
+        for pid in self.killed.take() {
+            self.processes.remove(&pid);
+        }
+
+        for pid in snapshot.exited {
+            self.processes.remove(&Pid::from_u32(pid));
+        }
+
+        for process in snapshot.started {
+            self.processes.insert(Pid::from_u32(process.pid), (process, snapshot.time));
+        }
+
+        Ok(ProcessTree::build(self.processes.values()
+            .map(|(process, recorded)| process.to_process(snapshot.time.saturating_sub(*recorded).max(0) as u64))
+            .collect()))
+    }
+
+    fn kill(&self, pid: Pid) -> bool {
+        println!("Replay : process {} would be killed", pid);
+        self.killed.borrow_mut().push(pid);
+        true
+    }
+}
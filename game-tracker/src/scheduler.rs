@@ -1,5 +1,5 @@
-use std::thread;
-use std::time::{Duration, Instant};
+use std::time::Duration;
+use crate::clock::SharedClock;
 use crate::errors::{Error};
 use crate::session::DailyGamingSession;
 use crate::subtasks::SubTask;
@@ -7,23 +7,17 @@ use crate::tracker::GamingTracker;
 
 pub struct GameTrackerScheduler {
     frequency: Duration,
+    clock: SharedClock,
     tracker: GamingTracker,
     sub_tasks: Vec<Box<dyn SubTask>>,
 }
 
 impl GameTrackerScheduler {
 
-    fn from(frequence: Duration) -> Self {
-        GameTrackerScheduler {
-            frequency: frequence,
-            tracker: GamingTracker::new(),
-            sub_tasks: Vec::new()
-        }
-    }
-
-    pub fn using(frequence: Duration, tracker: GamingTracker) -> Self {
+    pub fn using(frequence: Duration, tracker: GamingTracker, clock: SharedClock) -> Self {
         GameTrackerScheduler {
             frequency: frequence,
+            clock,
             tracker,
             sub_tasks: Vec::new()
         }
@@ -39,22 +33,41 @@ impl GameTrackerScheduler {
         self
     }
 
+    pub fn tracker(&self) -> &GamingTracker {
+        &self.tracker
+    }
+
+    pub fn tracker_mut(&mut self) -> &mut GamingTracker {
+        &mut self.tracker
+    }
+
+    /// Scan processes once and execute every SubTask
+    pub fn tick(&mut self) -> Result<(), Error> {
+        // update tracker
+        self.tracker.refresh()?;
+
+        // execute SubTasks
+        for sub_task in self.sub_tasks.iter_mut() {
+            sub_task.execute(&mut self.tracker)?;
+        }
+
+        Ok(())
+    }
+
     pub fn start(&mut self) -> Result<(), Error> {
         loop {
             // time execution
-            let start = Instant::now();
-
-            // update tracker
-            self.tracker.refresh()?;
+            let start = self.clock.monotonic();
 
-            // execute SubTasks
-            for sub_task in self.sub_tasks.iter_mut() {
-                sub_task.execute(&mut self.tracker)?;
+            match self.tick() {
+                // replays stop at the end of the recording
+                Err(Error::EndOfRecordingError) => return Ok(()),
+                result => result?,
             }
 
             // optional wait
-            if let Some(remainder) = self.frequency.checked_sub(start.elapsed()) {
-                thread::sleep(remainder);
+            if let Some(remainder) = self.frequency.checked_sub(self.clock.monotonic() - start) {
+                self.clock.sleep(remainder);
             }
         }
     }
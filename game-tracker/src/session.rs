@@ -1,49 +1,433 @@
-use chrono::{DateTime, Duration, Local};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Timelike, Weekday};
+use serde::Deserialize;
+use crate::clock::SharedClock;
 use crate::errors::Error;
+use crate::time::{format_duration, DurationParser};
 
-fn calculate_end_of_day(day: DateTime<Local>) -> Result<DateTime<Local>, Error> {
-    (day + Duration::days(1))
-        .date()
+/// Next midnight after `day`. When midnight doesn't exist (DST starting at midnight),
+/// the day ends at the first valid time after it.
+fn calculate_end_of_day<Tz: TimeZone>(day: DateTime<Tz>) -> Result<DateTime<Tz>, Error> {
+    let midnight = (day.date_naive() + Duration::days(1))
         .and_hms_opt(0, 0, 0)
-        .ok_or_else(|| Error::CalculateEndOfDayError)
+        .ok_or(Error::CalculateEndOfDayError)?;
+
+    (0..=8)
+        .map(|quarter| midnight + Duration::minutes(15 * quarter))
+        .find_map(|time| time.and_local_timezone(day.timezone()).earliest())
+        .ok_or(Error::CalculateEndOfDayError)
+}
+
+/// End of `day` in local time (see `calculate_end_of_day`)
+pub(crate) fn end_of_local_day(day: NaiveDate) -> Result<DateTime<Local>, Error> {
+    // noon exists in every timezone (DST changes happen at night)
+    let noon = day.and_hms_opt(12, 0, 0)
+        .and_then(|noon| noon.and_local_timezone(Local).earliest())
+        .ok_or(Error::CalculateEndOfDayError)?;
+
+    calculate_end_of_day(noon)
+}
+
+/// Period a play budget applies to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum BudgetPeriod {
+    Daily,
+    Weekly,
+    Monthly,
+}
+
+impl BudgetPeriod {
+
+    /// First day of the period containing `day`
+    pub fn start(self, day: NaiveDate, week_start: Weekday) -> NaiveDate {
+        match self {
+            BudgetPeriod::Daily => day,
+            BudgetPeriod::Weekly => day - Duration::days(day.weekday().days_since(week_start) as i64),
+            BudgetPeriod::Monthly => day.with_day(1).unwrap_or(day),
+        }
+    }
+}
+
+impl fmt::Display for BudgetPeriod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BudgetPeriod::Daily => write!(f, "daily"),
+            BudgetPeriod::Weekly => write!(f, "weekly"),
+            BudgetPeriod::Monthly => write!(f, "monthly"),
+        }
+    }
+}
+
+/// Time that can be played during a period (today's time played is kept by the tracker)
+#[derive(Debug, Clone)]
+pub struct Budget {
+    period: BudgetPeriod,
+    duration: Duration,
+
+    /// Time played during the period before today
+    played_before: Duration,
+}
+
+impl Budget {
+
+    pub fn period(&self) -> BudgetPeriod {
+        self.period
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    /// Time played during the period, with `time_played` today
+    pub fn played(&self, time_played: Duration) -> Duration {
+        self.played_before + time_played
+    }
+
+    pub fn remaining(&self, time_played: Duration) -> Duration {
+        self.duration - self.played(time_played)
+    }
+
+    pub fn is_exhausted(&self, time_played: Duration) -> bool {
+        self.duration < self.played(time_played)
+    }
+}
+
+/// Daily budget of some days of the week and of specific dates (holidays...), read from
+/// the `[session.schedule]` section (ex.: `saturday = "3h"`, `"2026-12-25" = "5h"`).
+/// Dates have priority over days of the week.
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(try_from = "BTreeMap<String, DurationParser>")]
+pub struct Schedule {
+
+    /// Days from monday
+    weekdays: BTreeMap<u32, Duration>,
+    dates: BTreeMap<NaiveDate, Duration>,
+}
+
+impl Schedule {
+
+    /// Daily budget of `day` (if the schedule has one)
+    pub fn duration(&self, day: NaiveDate) -> Option<Duration> {
+        self.dates.get(&day)
+            .or_else(|| self.weekdays.get(&day.weekday().num_days_from_monday()))
+            .copied()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.weekdays.is_empty() && self.dates.is_empty()
+    }
+}
+
+impl TryFrom<BTreeMap<String, DurationParser>> for Schedule {
+    type Error = Error;
+
+    fn try_from(value: BTreeMap<String, DurationParser>) -> Result<Self, Self::Error> {
+        let mut schedule = Schedule::default();
+
+        for (day, duration) in value {
+            if let Ok(weekday) = Weekday::from_str(&day) {
+                schedule.weekdays.insert(weekday.num_days_from_monday(), duration.to_duration());
+            } else if let Ok(date) = NaiveDate::parse_from_str(&day, "%Y-%m-%d") {
+                schedule.dates.insert(date, duration.to_duration());
+            } else {
+                return Err(Error::ScheduleParserError(day));
+            }
+        }
+
+        Ok(schedule)
+    }
+}
+
+impl fmt::Display for Schedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let weekdays = self.weekdays.iter()
+            .map(|(day, duration)| (Weekday::try_from(*day as u8).map(|day| day.to_string()).unwrap_or_default(), duration));
+        let dates = self.dates.iter()
+            .map(|(date, duration)| (date.to_string(), duration));
+        let days = weekdays.chain(dates)
+            .map(|(day, duration)| format!("{} : {}", day, format_duration(duration)))
+            .collect::<Vec<String>>();
+
+        write!(f, "{}", days.join(", "))
+    }
+}
+
+const SECONDS_PER_DAY: u32 = 24 * 60 * 60;
+
+/// Hours when gaming is allowed (ex.: "18:00-23:00" - "24:00" ends the window at midnight)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimeWindow {
+
+    /// Seconds from midnight
+    start: u32,
+    end: u32,
+}
+
+impl TimeWindow {
+
+    fn contains(&self, seconds: u32) -> bool {
+        self.start <= seconds && seconds < self.end
+    }
+}
+
+impl FromStr for TimeWindow {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let seconds = |time: &str| match time.trim() {
+            "24:00" => Some(SECONDS_PER_DAY),
+            time => NaiveTime::parse_from_str(time, "%H:%M").ok().map(|time| time.num_seconds_from_midnight()),
+        };
+
+        s.split_once('-')
+            .and_then(|(start, end)| Some(TimeWindow { start: seconds(start)?, end: seconds(end)? }))
+            .filter(|window| window.start < window.end)
+            .ok_or(Error::AllowedHoursParserError(s.to_string()))
+    }
+}
+
+impl fmt::Display for TimeWindow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:02}:{:02}-{:02}:{:02}", self.start / 3600, self.start % 3600 / 60, self.end / 3600, self.end % 3600 / 60)
+    }
+}
+
+/// Hours when gaming is allowed on some days of the week, dates or every other day (`default`),
+/// read from the `[session.allowed_hours]` section (ex.: `default = ["18:00-23:00"]`,
+/// `saturday = ["10:00-12:00", "14:00-23:00"]`). Days without windows have no restriction.
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(try_from = "BTreeMap<String, Vec<String>>")]
+pub struct AllowedHours {
+    default: Option<Vec<TimeWindow>>,
+
+    /// Days from monday
+    weekdays: BTreeMap<u32, Vec<TimeWindow>>,
+    dates: BTreeMap<NaiveDate, Vec<TimeWindow>>,
+}
+
+impl AllowedHours {
+
+    /// Windows of `day` (None when gaming is allowed all day)
+    pub fn windows(&self, day: NaiveDate) -> Option<&[TimeWindow]> {
+        self.dates.get(&day)
+            .or_else(|| self.weekdays.get(&day.weekday().num_days_from_monday()))
+            .or(self.default.as_ref())
+            .map(Vec::as_slice)
+    }
+
+    /// Time left before gaming stops being allowed : zero outside the windows, None when
+    /// gaming is allowed until the end of the day (a window ending at midnight continues
+    /// with the window of the next day starting at midnight).
+    pub fn time_left(&self, now: NaiveDateTime) -> Option<Duration> {
+        let seconds = now.time().num_seconds_from_midnight();
+        let Some(window) = self.windows(now.date())?.iter().find(|window| window.contains(seconds)) else {
+            return Some(Duration::zero());
+        };
+
+        let left = Duration::seconds((window.end - seconds) as i64);
+        if window.end < SECONDS_PER_DAY {
+            return Some(left);
+        }
+
+        let tomorrow = now.date().succ_opt().and_then(|tomorrow| self.windows(tomorrow))?;
+        Some(left + Duration::seconds(tomorrow.iter()
+            .find(|window| window.start == 0)
+            .map(|window| window.end as i64)
+            .unwrap_or_default()))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.default.is_none() && self.weekdays.is_empty() && self.dates.is_empty()
+    }
+}
+
+impl TryFrom<BTreeMap<String, Vec<String>>> for AllowedHours {
+    type Error = Error;
+
+    fn try_from(value: BTreeMap<String, Vec<String>>) -> Result<Self, Self::Error> {
+        let mut allowed_hours = AllowedHours::default();
+
+        for (day, windows) in value {
+            let windows = windows.iter()
+                .map(|window| TimeWindow::from_str(window))
+                .collect::<Result<Vec<TimeWindow>, Error>>()?;
+
+            if day == "default" {
+                allowed_hours.default = Some(windows);
+            } else if let Ok(weekday) = Weekday::from_str(&day) {
+                allowed_hours.weekdays.insert(weekday.num_days_from_monday(), windows);
+            } else if let Ok(date) = NaiveDate::parse_from_str(&day, "%Y-%m-%d") {
+                allowed_hours.dates.insert(date, windows);
+            } else {
+                return Err(Error::AllowedHoursParserError(day));
+            }
+        }
+
+        Ok(allowed_hours)
+    }
 }
 
-#[derive(Debug, Default, Clone)]
+impl fmt::Display for AllowedHours {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let windows = |windows: &Vec<TimeWindow>| windows.iter()
+            .map(TimeWindow::to_string)
+            .collect::<Vec<String>>()
+            .join(" ");
+        let default = self.default.iter()
+            .map(|default| ("default".to_string(), default));
+        let weekdays = self.weekdays.iter()
+            .map(|(day, windows)| (Weekday::try_from(*day as u8).map(|day| day.to_string()).unwrap_or_default(), windows));
+        let dates = self.dates.iter()
+            .map(|(date, windows)| (date.to_string(), windows));
+        let days = default.chain(weekdays).chain(dates)
+            .map(|(day, day_windows)| format!("{} : {}", day, windows(day_windows)))
+            .collect::<Vec<String>>();
+
+        write!(f, "{}", days.join(", "))
+    }
+}
+
+/// Gaming session of the day. It ends as soon as one of its budgets (daily, weekly or monthly)
+/// is exhausted, and restarts at midnight.
+#[derive(Debug, Clone)]
 pub struct DailyGamingSession {
+    clock: SharedClock,
     start_time: DateTime<Local>,
     end_of_day: DateTime<Local>,
     session_ended: bool,
-    duration: Duration,
+    budgets: Vec<Budget>,
+    week_start: Weekday,
+
+    /// Daily budget of the days missing from the schedule
+    daily_duration: Option<Duration>,
+    schedule: Schedule,
+    allowed_hours: AllowedHours,
 }
 
 impl DailyGamingSession {
-    pub fn new() -> Result<DailyGamingSession, Error> {
-        let start_time = Local::now();
+    pub fn new(clock: SharedClock) -> Result<DailyGamingSession, Error> {
+        let start_time = clock.now();
         let end_of_day = calculate_end_of_day(start_time)?;
 
         Ok(
             Self {
+                clock,
                 start_time,
                 end_of_day,
                 session_ended: false,
-                duration: Duration::seconds(0),
+                budgets: vec![],
+                week_start: Weekday::Mon,
+                daily_duration: None,
+                schedule: Schedule::default(),
+                allowed_hours: AllowedHours::default(),
             }
         )
     }
 
-    pub fn from_duration(duration: Duration) -> Result<DailyGamingSession, Error> {
-        let mut session = DailyGamingSession::new()?;
-        session.duration = duration;
+    /// Session with a daily budget only
+    pub fn from_duration(duration: Duration, clock: SharedClock) -> Result<DailyGamingSession, Error> {
+        Ok(DailyGamingSession::new(clock)?.with_budget(BudgetPeriod::Daily, duration))
+    }
 
-        Ok(session)
+    /// Add a budget (replacing the budget of the same period). The daily budget is only
+    /// used on the days missing from the schedule.
+    pub fn with_budget(mut self, period: BudgetPeriod, duration: Duration) -> Self {
+        if period == BudgetPeriod::Daily {
+            self.daily_duration = Some(duration);
+        }
+
+        self.set_budget(period, Some(duration));
+        self.apply_schedule();
+        self
+    }
+
+    /// Daily budget of each day of the week and dates (see `Schedule`)
+    pub fn with_schedule(mut self, schedule: Schedule) -> Self {
+        self.schedule = schedule;
+        self.apply_schedule();
+        self
+    }
+
+    /// Hours when gaming is allowed (see `AllowedHours`)
+    pub fn with_allowed_hours(mut self, allowed_hours: AllowedHours) -> Self {
+        self.allowed_hours = allowed_hours;
+        self
+    }
+
+    pub fn allowed_hours(&self) -> &AllowedHours {
+        &self.allowed_hours
+    }
+
+    /// Time left before gaming stops being allowed (None if it's allowed until the end of the day)
+    pub fn time_left_before_curfew(&self) -> Option<Duration> {
+        self.allowed_hours.time_left(self.clock.now().naive_local())
+    }
+
+    /// When gaming stops being allowed (None if it's allowed until the end of the day)
+    pub fn curfew(&self) -> Option<NaiveDateTime> {
+        let now = self.clock.now().naive_local();
+        self.allowed_hours.time_left(now).map(|left| now + left)
+    }
+
+    /// Gaming isn't allowed at this time of the day
+    pub fn is_curfew(&self) -> bool {
+        self.time_left_before_curfew().is_some_and(|left| left <= Duration::zero())
+    }
+
+    fn set_budget(&mut self, period: BudgetPeriod, duration: Option<Duration>) {
+        self.budgets.retain(|budget| budget.period != period);
+        if let Some(duration) = duration {
+            self.budgets.push(Budget { period, duration, played_before: Duration::zero() });
+            self.budgets.sort_by_key(|budget| budget.period);
+        }
+    }
+
+    /// Set the daily budget of the session's day
+    fn apply_schedule(&mut self) {
+        let duration = self.schedule.duration(self.day()).or(self.daily_duration);
+        self.set_budget(BudgetPeriod::Daily, duration);
+    }
+
+    /// First day of weekly budgets (monday by default)
+    pub fn with_week_start(mut self, week_start: Weekday) -> Self {
+        self.week_start = week_start;
+        self
+    }
+
+    pub fn budgets(&self) -> &[Budget] {
+        &self.budgets
+    }
+
+    /// Day of the session (local date)
+    pub fn day(&self) -> NaiveDate {
+        self.start_time.date_naive()
+    }
+
+    /// First day of the current `period`
+    pub fn period_start(&self, period: BudgetPeriod) -> NaiveDate {
+        period.start(self.day(), self.week_start)
+    }
+
+    /// Set the time played during the period before today (restored from the statistics)
+    pub fn restore_played(&mut self, period: BudgetPeriod, played_before: Duration) {
+        for budget in self.budgets.iter_mut().filter(|budget| budget.period == period) {
+            budget.played_before = played_before;
+        }
+    }
+
+    /// Budget with the least time remaining (the shortest period first) - the one ending the session
+    pub fn binding_budget(&self, time_played: Duration) -> Option<&Budget> {
+        self.budgets.iter().min_by_key(|budget| budget.remaining(time_played))
     }
 
     pub fn is_session_over(&self, time_played: Duration) -> bool {
-        self.duration < time_played
+        self.budgets.iter().any(|budget| budget.is_exhausted(time_played))
     }
 
     pub fn is_passed_midnight(&self) -> bool {
-        Local::now() >= self.end_of_day
+        self.clock.now() >= self.end_of_day
     }
 
     pub fn is_session_ended(&self) -> bool {
@@ -54,12 +438,205 @@ impl DailyGamingSession {
         self.session_ended = true;
     }
 
-    pub fn restart_session(&mut self) -> Result<(), Error> {
-        self.start_time = Local::now();
+    /// Start the session of a new day (`time_played` is the time played the previous day)
+    pub fn restart_session(&mut self, time_played: Duration) -> Result<(), Error> {
+        let previous_day = self.day();
+        self.start_time = self.clock.now();
         self.session_ended = false;
         self.end_of_day = calculate_end_of_day(self.start_time)?;
 
+        let (day, week_start) = (self.day(), self.week_start);
+        for budget in self.budgets.iter_mut() {
+            budget.played_before = if budget.period.start(previous_day, week_start) == budget.period.start(day, week_start) {
+                budget.played_before + time_played
+            } else {
+                Duration::zero()
+            };
+        }
+
+        self.apply_schedule();
         Ok(())
     }
 
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod session_tests {
+    use super::*;
+    use chrono_tz::America::Sao_Paulo;
+    use chrono_tz::Europe::Paris;
+    use crate::clock::ManualClock;
+
+    fn local(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> DateTime<Local> {
+        Local.with_ymd_and_hms(year, month, day, hour, minute, 0).single().expect("valid local time")
+    }
+
+    #[test]
+    fn test_midnight_rollover() {
+        let clock = ManualClock::new(local(2026, 1, 10, 23, 50));
+        let mut session = DailyGamingSession::from_duration(Duration::hours(1), clock.clone())
+            .expect("no errors!");
+
+        clock.advance(std::time::Duration::from_secs(5 * 60));
+        assert!(!session.is_passed_midnight());
+
+        session.end_session();
+        clock.advance(std::time::Duration::from_secs(10 * 60));
+        assert!(session.is_passed_midnight());
+
+        session.restart_session(Duration::hours(2)).expect("no errors!");
+        assert!(!session.is_session_ended());
+        assert!(!session.is_passed_midnight());
+
+        clock.advance(std::time::Duration::from_secs(24 * 60 * 60));
+        assert!(session.is_passed_midnight());
+    }
+
+    #[test]
+    fn test_budget_periods() {
+        // saturday
+        let day = NaiveDate::from_ymd_opt(2026, 1, 10).unwrap();
+        assert_eq!(BudgetPeriod::Daily.start(day, Weekday::Mon), day);
+        assert_eq!(BudgetPeriod::Weekly.start(day, Weekday::Mon), NaiveDate::from_ymd_opt(2026, 1, 5).unwrap());
+        assert_eq!(BudgetPeriod::Weekly.start(day, Weekday::Sat), day);
+        assert_eq!(BudgetPeriod::Weekly.start(day, Weekday::Sun), NaiveDate::from_ymd_opt(2026, 1, 4).unwrap());
+        assert_eq!(BudgetPeriod::Monthly.start(day, Weekday::Mon), NaiveDate::from_ymd_opt(2026, 1, 1).unwrap());
+    }
+
+    #[test]
+    fn test_weekly_budget() {
+        // saturday, weeks starting on sunday
+        let clock = ManualClock::new(local(2026, 1, 10, 12, 0));
+        let mut session = DailyGamingSession::new(clock.clone()).expect("no errors!")
+            .with_week_start(Weekday::Sun)
+            .with_budget(BudgetPeriod::Weekly, Duration::hours(5))
+            .with_budget(BudgetPeriod::Daily, Duration::hours(2));
+        session.restore_played(BudgetPeriod::Weekly, Duration::hours(3));
+
+        assert_eq!(session.binding_budget(Duration::minutes(30)).map(Budget::period), Some(BudgetPeriod::Daily));
+        assert_eq!(session.binding_budget(Duration::hours(1)).map(Budget::period), Some(BudgetPeriod::Daily));
+        assert!(!session.is_session_over(Duration::hours(2)));
+        assert!(session.is_session_over(Duration::hours(2) + Duration::seconds(1)));
+
+        // sunday : a new week starts
+        clock.advance(std::time::Duration::from_secs(12 * 60 * 60));
+        session.restart_session(Duration::hours(2)).expect("no errors!");
+        assert_eq!(session.budgets()[1].played(Duration::zero()), Duration::zero());
+
+        // monday : the time played sunday counts
+        clock.advance(std::time::Duration::from_secs(24 * 60 * 60));
+        session.restart_session(Duration::hours(2)).expect("no errors!");
+        assert_eq!(session.binding_budget(Duration::hours(1)).map(Budget::period), Some(BudgetPeriod::Daily));
+        assert_eq!(session.binding_budget(Duration::hours(1)).map(|budget| budget.remaining(Duration::hours(1))), Some(Duration::hours(1)));
+
+        session.restore_played(BudgetPeriod::Weekly, Duration::hours(4));
+        let binding = session.binding_budget(Duration::minutes(30)).expect("budgets set");
+        assert_eq!(binding.period(), BudgetPeriod::Weekly);
+        assert_eq!(binding.remaining(Duration::minutes(30)), Duration::minutes(30));
+    }
+
+    #[test]
+    fn test_schedule() {
+        let schedule = Schedule::try_from(BTreeMap::from([
+            ("saturday".to_string(), "3h".parse::<DurationParser>().unwrap()),
+            ("sun".to_string(), "3h".parse::<DurationParser>().unwrap()),
+            ("2026-01-12".to_string(), "5h".parse::<DurationParser>().unwrap()),
+        ])).expect("no errors!");
+
+        // saturday
+        let clock = ManualClock::new(local(2026, 1, 10, 12, 0));
+        let mut session = DailyGamingSession::from_duration(Duration::hours(1), clock.clone()).expect("no errors!")
+            .with_budget(BudgetPeriod::Weekly, Duration::hours(10))
+            .with_schedule(schedule);
+        assert_eq!(session.budgets()[0].duration(), Duration::hours(3));
+
+        let next_day = |session: &mut DailyGamingSession| {
+            clock.advance(std::time::Duration::from_secs(24 * 60 * 60));
+            session.restart_session(Duration::hours(1)).expect("no errors!");
+            session.budgets().iter()
+                .find(|budget| budget.period() == BudgetPeriod::Daily)
+                .map(Budget::duration)
+        };
+
+        // sunday, monday (holiday), then tuesday (default duration)
+        assert_eq!(next_day(&mut session), Some(Duration::hours(3)));
+        assert_eq!(next_day(&mut session), Some(Duration::hours(5)));
+        assert_eq!(next_day(&mut session), Some(Duration::hours(1)));
+        // the week started monday
+        assert_eq!(session.budgets()[1].played(Duration::zero()), Duration::hours(1));
+
+        let invalid = Schedule::try_from(BTreeMap::from([("holidays".to_string(), DurationParser::default())]));
+        assert!(invalid.is_err());
+    }
+
+    #[test]
+    fn test_allowed_hours() {
+        let allowed_hours = AllowedHours::try_from(BTreeMap::from([
+            ("default".to_string(), vec!["18:00-23:00".to_string()]),
+            ("fri".to_string(), vec!["18:00-24:00".to_string()]),
+            ("saturday".to_string(), vec!["00:00-01:00".to_string(), "10:00-12:00".to_string(), "14:00-24:00".to_string()]),
+            ("sunday".to_string(), vec![]),
+            ("2026-01-12".to_string(), vec!["08:00-24:00".to_string()]),
+        ])).expect("no errors!");
+        let at = |day: u32, hour: u32, minute: u32| {
+            NaiveDate::from_ymd_opt(2026, 1, day).unwrap().and_hms_opt(hour, minute, 0).unwrap()
+        };
+
+        // thursday
+        assert_eq!(allowed_hours.time_left(at(8, 17, 0)), Some(Duration::zero()));
+        assert_eq!(allowed_hours.time_left(at(8, 22, 50)), Some(Duration::minutes(10)));
+        assert_eq!(allowed_hours.time_left(at(8, 23, 0)), Some(Duration::zero()));
+
+        // friday night continues until 1am
+        assert_eq!(allowed_hours.time_left(at(9, 23, 0)), Some(Duration::hours(2)));
+        assert_eq!(allowed_hours.time_left(at(10, 11, 0)), Some(Duration::hours(1)));
+
+        // sunday : never, then a holiday
+        assert_eq!(allowed_hours.time_left(at(11, 20, 0)), Some(Duration::zero()));
+        assert_eq!(allowed_hours.time_left(at(12, 9, 0)), Some(Duration::hours(15)));
+
+        assert!(TimeWindow::from_str("23:00-18:00").is_err());
+        assert_eq!(TimeWindow::from_str("9:05-24:00").expect("no errors!").to_string(), "09:05-24:00");
+        assert!(AllowedHours::try_from(BTreeMap::from([("weekend".to_string(), vec![])])).is_err());
+
+        let clock = ManualClock::new(local(2026, 1, 8, 17, 50));
+        let session = DailyGamingSession::new(clock.clone()).expect("no errors!")
+            .with_allowed_hours(allowed_hours);
+        assert!(session.is_curfew());
+
+        clock.advance(std::time::Duration::from_secs(10 * 60));
+        assert!(!session.is_curfew());
+        assert_eq!(session.time_left_before_curfew(), Some(Duration::hours(5)));
+    }
+
+    #[test]
+    fn test_clock_set_back() {
+        let clock = ManualClock::new(local(2026, 1, 10, 20, 0));
+        let session = DailyGamingSession::new(clock.clone()).expect("no errors!");
+
+        // going back to yesterday doesn't start a new day
+        clock.set_now(local(2026, 1, 9, 23, 0));
+        assert!(!session.is_passed_midnight());
+
+        clock.set_now(local(2026, 1, 11, 0, 0));
+        assert!(session.is_passed_midnight());
+    }
+
+    #[test]
+    fn test_end_of_day_dst() {
+        // DST started at midnight : 2018-11-04 00:00 didn't exist in Sao Paulo
+        let day = Sao_Paulo.with_ymd_and_hms(2018, 11, 3, 10, 0, 0).unwrap();
+        let end = calculate_end_of_day(day).expect("no errors!");
+        assert_eq!(end, Sao_Paulo.with_ymd_and_hms(2018, 11, 4, 1, 0, 0).unwrap());
+
+        // DST ended : the day lasts 25 hours
+        let day = Paris.with_ymd_and_hms(2026, 10, 25, 0, 0, 0).unwrap();
+        let end = calculate_end_of_day(day).expect("no errors!");
+        assert_eq!(end.signed_duration_since(day), Duration::hours(25));
+
+        // DST started : the day lasts 23 hours
+        let day = Paris.with_ymd_and_hms(2026, 3, 29, 0, 0, 0).unwrap();
+        let end = calculate_end_of_day(day).expect("no errors!");
+        assert_eq!(end.signed_duration_since(day), Duration::hours(23));
+    }
+}
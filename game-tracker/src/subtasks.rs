@@ -1,11 +1,17 @@
-use std::time::Instant;
-use chrono::{DateTime, Local};
-use notify_rust::Notification;
+use std::collections::HashSet;
+use std::time::{Duration, Instant, SystemTime};
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime};
+use sysinfo::Pid;
+use crate::clock::SharedClock;
+use crate::session::{Budget, BudgetPeriod};
+use crate::config::{Config, ConfigSource};
 use crate::errors::{Error, TimeTampering};
-use crate::process_tree::ProcessInfo;
 use crate::time::format_duration;
 use crate::tracker::GamingTracker;
 
+/// Warning before the end of the allowed hours (see `CurfewGameKiller`)
+const CURFEW_WARNING: chrono::Duration = chrono::Duration::minutes(10);
+
 pub trait SubTask {
 
     /// Main function that executes SubTask
@@ -13,15 +19,6 @@ pub trait SubTask {
 
 }
 
-fn notify(msg: &str) -> Result<(), Error> {
-    Notification::new()
-        .summary("WARNING")
-        .body(msg)
-        .show()?;
-
-    Ok(())
-}
-
 
 pub struct GamesLogger;
 
@@ -33,7 +30,7 @@ impl GamesLogger {
 
 impl SubTask for GamesLogger {
     fn execute(&mut self, tracker: &mut GamingTracker) -> Result<(), Error> {
-        if tracker.gametime_tracker().len() == 0 {
+        if tracker.gametime_tracker().is_empty() {
             println!("No games have been found yet!");
             return Ok(())
         }
@@ -42,14 +39,26 @@ impl SubTask for GamesLogger {
         output += "All games found: \n";
 
         let games_found = tracker.gametime_tracker().iter()
-            .flat_map(|(game_name, processes)| {
-                processes.iter().map(move |process| (process, game_name))
+            .flat_map(|(game_name, game_time)| {
+                game_time.processes().iter().map(move |process| (process, game_name))
             });
 
-        for (proc, game_name) in games_found {
+        for (proc, game) in games_found {
             let dur = chrono::Duration::seconds(proc.run_time() as i64);
-            output += format!("{} '{}' has been running for: {}\n",
-                              proc.pid(), game_name, format_duration(&dur)
+            let category = game.category()
+                .map(|category| format!(" [{}]", category))
+                .unwrap_or_default();
+
+            output += format!("{} '{}'{} has been running for: {}\n",
+                              proc.pid(), game, category, format_duration(&dur)
+            ).as_str()
+        }
+
+        let time_played = tracker.total_time_played();
+        if let Some(budget) = tracker.session().and_then(|session| session.binding_budget(time_played)) {
+            output += format!("Time played today: {} - {} budget: {} left\n",
+                              format_duration(&time_played), budget.period(),
+                              format_duration(&budget.remaining(time_played).max(chrono::Duration::zero()))
             ).as_str()
         }
 
@@ -69,79 +78,190 @@ impl SessionEndGameKiller {
 impl SubTask for SessionEndGameKiller {
     fn execute(&mut self, tracker: &mut GamingTracker) -> Result<(), Error> {
         if let Some(session) = tracker.session() && session.is_session_ended() {
-            notify("Play time's over buddy! Go touch grass :-)")?;
+            match session.binding_budget(tracker.total_time_played()) {
+                Some(budget) => tracker.notify(format!("Play time's over buddy! Go touch grass :-) ({} budget of {} used)",
+                                                       budget.period(), format_duration(&budget.duration())).as_str()),
+                None => tracker.notify("Play time's over buddy! Go touch grass :-)"),
+            }?;
 
-            let known_games = tracker.gametime_tracker().iter()
-                .flat_map(|(_, proc)| proc.into_iter());
+            for proc in tracker.running_processes() {
+                tracker.kill(proc)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Kills games running outside the allowed hours (see `[session.allowed_hours]`). The user
+/// is warned `CURFEW_WARNING` before each cutoff while a game is running, and notified once
+/// per game process started outside the allowed hours; with `monitor_only`, nothing is killed.
+pub struct CurfewGameKiller {
+    monitor_only: bool,
+    notified: HashSet<Pid>,
+    warned: Option<NaiveDateTime>,
+}
+
+impl CurfewGameKiller {
+    pub fn new(monitor_only: bool) -> Box<Self> {
+        Box::new(CurfewGameKiller { monitor_only, notified: HashSet::new(), warned: None })
+    }
+}
 
-            for proc in known_games {
+impl SubTask for CurfewGameKiller {
+    fn execute(&mut self, tracker: &mut GamingTracker) -> Result<(), Error> {
+        let Some(session) = tracker.session() else {
+            return Ok(());
+        };
+
+        if !session.is_curfew() {
+            self.notified.clear();
+
+            // warn once before each cutoff
+            let game_running = tracker.running_processes().next().is_some();
+            if let Some(left) = session.time_left_before_curfew()
+                && game_running && left <= CURFEW_WARNING
+                && let Some(cutoff) = session.curfew()
+                && self.warned != Some(cutoff) {
+                println!("Warning : gaming stops at {}", cutoff.format("%H:%M"));
+                self.warned = Some(cutoff);
+
+                tracker.notify(
+                    format!(
+                        "Gaming stops at {} ({} left)",
+                        cutoff.format("%H:%M"), format_duration(&left)
+                    ).as_str()
+                )?;
+            }
+
+            return Ok(());
+        }
+
+        let mut launched = false;
+        for proc in tracker.running_processes() {
+            launched |= self.notified.insert(proc.pid());
+        }
+
+        if launched {
+            let allowed = session.allowed_hours().windows(session.day())
+                .filter(|windows| !windows.is_empty())
+                .map(|windows| windows.iter().map(|window| window.to_string()).collect::<Vec<String>>().join(", "))
+                .unwrap_or("never".to_string());
+            tracker.notify(format!("Gaming isn't allowed now (allowed today: {})", allowed).as_str())?;
+        }
+
+        if !self.monitor_only {
+            for proc in tracker.running_processes() {
                 tracker.kill(proc)?;
             }
         }
+
         Ok(())
     }
 }
 
+/// Kills games played longer than their own limit (see `limit` of `[[games]]`) - limits are daily
+pub struct GameLimitKiller {
+    notified: HashSet<String>,
+    day: Option<NaiveDate>,
+}
+
+impl GameLimitKiller {
+    pub fn new() -> Box<Self> {
+        Box::new(GameLimitKiller { notified: HashSet::new(), day: None })
+    }
+}
+
+impl SubTask for GameLimitKiller {
+    fn execute(&mut self, tracker: &mut GamingTracker) -> Result<(), Error> {
+        // notify again once the time played is reset
+        if self.day != Some(tracker.day()) {
+            self.day = Some(tracker.day());
+            self.notified.clear();
+        }
+
+        for game in tracker.games_over_limit() {
+            if self.notified.insert(game.name().to_string()) {
+                tracker.notify(format!("Play time's over for {} :-)", game).as_str())?;
+            }
+
+            if let Some(game_time) = tracker.gametime_tracker().get(game) {
+                for proc in game_time.processes() {
+                    tracker.kill(proc)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Warns once per budget period when `threshold`% of a budget (daily, weekly or monthly)
+/// has been played
 pub struct WarnSessionEnding {
-    was_warned: bool,
+    warned: HashSet<(BudgetPeriod, NaiveDate)>,
     threshold: f64,
-    duration: chrono::Duration,
 }
 
 impl WarnSessionEnding {
-    pub fn from(threshold: f64, session_duration: i64) -> Box<Self> {
-        let value = chrono::Duration::seconds(
-            ((threshold / 100_f64) * session_duration as f64).floor() as i64
-        );
-
+    pub fn new(threshold: f64) -> Box<Self> {
         Box::new(Self {
-            duration: value,
-            was_warned: false,
+            warned: HashSet::new(),
             threshold,
         })
     }
 
-    pub fn duration(&self) -> chrono::Duration {
-        self.duration
+    /// Time played before warning of the end of `budget`
+    fn warning_after(&self, budget: &Budget) -> chrono::Duration {
+        chrono::Duration::seconds(
+            ((self.threshold / 100_f64) * budget.duration().num_seconds() as f64).floor() as i64
+        )
     }
-
 }
 
 impl SubTask for WarnSessionEnding {
     fn execute(&mut self, tracker: &mut GamingTracker) -> Result<(), Error> {
-        if let Some(session) = tracker.session() {
-            if !self.was_warned && !session.is_session_ended()
-                && tracker.total_time_played() > self.duration {
-                println!("Warning threshold reached : {}", self.threshold);
-                self.was_warned = true;
+        let Some(session) = tracker.session() else {
+            return Ok(());
+        };
+
+        if session.is_session_ended() {
+            return Ok(());
+        }
+
+        let time_played = tracker.total_time_played();
+        for budget in session.budgets() {
+            let period = (budget.period(), session.period_start(budget.period()));
+            if budget.played(time_played) > self.warning_after(budget) && self.warned.insert(period) {
+                println!("Warning threshold reached : {}% of {} budget", self.threshold, budget.period());
 
-                notify(
+                tracker.notify(
                     format!(
-                        "{}% of session gaming played ({})",
-                        self.threshold, format_duration(&self.duration)
+                        "{}% of {} gaming budget played ({} left)",
+                        self.threshold, budget.period(), format_duration(&budget.remaining(time_played).max(chrono::Duration::zero()))
                     ).as_str()
                 )?;
             }
-
-        } else {
-            self.was_warned = false;
         }
 
         Ok(())
     }
 }
 
+/// Detects the wall clock moving faster than the monotonic clock (ex.: date set to tomorrow
+/// to start a new session)
 pub struct ClockTampering {
+    clock: SharedClock,
     start_time: DateTime<Local>,
     uptime: Instant,
     detected: bool
 }
 
 impl ClockTampering {
-    pub fn new() -> Box<Self> {
+    pub fn new(clock: SharedClock) -> Box<Self> {
         Box::new(Self {
-            start_time: Local::now(),
-            uptime: Instant::now(),
+            start_time: clock.now(),
+            uptime: clock.monotonic(),
+            clock,
             detected: false,
         })
     }
@@ -154,9 +274,10 @@ impl SubTask for ClockTampering {
             return Ok(());
         }
 
-        let clock_estimation = Local::now()
+        let clock_estimation = self.clock.now()
             .signed_duration_since(self.start_time).num_seconds() as u64;
-        let instant_estimation = self.uptime.elapsed().as_secs();
+        let instant_estimation = self.clock.monotonic()
+            .duration_since(self.uptime).as_secs();
 
         if clock_estimation > instant_estimation {
             self.detected = true;
@@ -167,6 +288,68 @@ impl SubTask for ClockTampering {
     }
 }
 
+/// Reloads the games configuration and rescans installed games while running
+/// (when the configuration file changes or every `interval`). Tracker and session
+/// settings are only read at startup.
+pub struct ConfigReloader {
+    clock: SharedClock,
+    source: ConfigSource,
+    interval: Duration,
+    last_reload: Instant,
+    last_modified: Option<SystemTime>,
+}
+
+impl ConfigReloader {
+    pub fn new(source: ConfigSource, interval: Duration, clock: SharedClock) -> Box<Self> {
+        Box::new(Self {
+            last_modified: source.modified(),
+            source,
+            interval,
+            last_reload: clock.monotonic(),
+            clock,
+        })
+    }
+
+    fn reload(&self, tracker: &mut GamingTracker) -> Result<(), Error> {
+        let previous_games = tracker.installed_game_names();
+        tracker.rescan(Config::load(&self.source)?)?;
+        let current_games = tracker.installed_game_names();
+
+        for added in current_games.difference(&previous_games) {
+            println!("New game found: {}", added);
+        }
+
+        for removed in previous_games.difference(&current_games) {
+            println!("Game removed: {}", removed);
+        }
+
+        Ok(())
+    }
+}
+
+impl SubTask for ConfigReloader {
+    fn execute(&mut self, tracker: &mut GamingTracker) -> Result<(), Error> {
+        let modified = self.source.modified();
+        let config_changed = modified != self.last_modified;
+        let rescan = !self.interval.is_zero() && self.clock.monotonic().duration_since(self.last_reload) >= self.interval;
+
+        if !config_changed && !rescan {
+            return Ok(());
+        }
+
+        self.last_modified = modified;
+        self.last_reload = self.clock.monotonic();
+
+        // an invalid configuration keeps the previous one
+        if let Err(e) = self.reload(tracker) {
+            println!("Could not reload configuration from {} - keeping previous configuration : {}",
+                     self.source, e);
+        }
+
+        Ok(())
+    }
+}
+
 pub struct RampageMode;
 
 impl RampageMode {
@@ -183,14 +366,37 @@ impl RampageMode {
 impl SubTask for RampageMode {
 
     fn execute(&mut self, tracker: &mut GamingTracker) -> Result<(), Error> {
-        let games: Vec<&ProcessInfo> = tracker.gametime_tracker().iter()
-            .flat_map(|(_, proc)| proc.into_iter())
-            .collect();
-
-        for game in games {
+        for game in tracker.running_processes() {
             tracker.kill(game)?;
         }
 
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod subtasks_tests {
+    use super::*;
+    use chrono::TimeZone;
+    use crate::clock::ManualClock;
+    use crate::process_source::ProcSource;
+
+    #[test]
+    fn test_clock_tampering() {
+        let clock = ManualClock::new(Local.with_ymd_and_hms(2026, 1, 10, 20, 0, 0).unwrap());
+        // processes are never scanned
+        let mut tracker = GamingTracker::with_source(Box::new(ProcSource::new(clock.clone())), clock.clone());
+        let mut tampering = ClockTampering::new(clock.clone());
+
+        clock.advance(Duration::from_secs(3 * 60 * 60));
+        tampering.execute(&mut tracker).expect("no errors!");
+
+        // jumping to tomorrow would start a new session
+        clock.set_now(Local.with_ymd_and_hms(2026, 1, 11, 0, 5, 0).unwrap());
+        let error = tampering.execute(&mut tracker).expect_err("should not work!");
+        assert!(matches!(error, Error::TimeTamperingError(TimeTampering::ClockTamperingError)));
+
+        // only reported once
+        tampering.execute(&mut tracker).expect("no errors!");
+    }
+}
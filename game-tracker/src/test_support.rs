@@ -0,0 +1,45 @@
+//! Helpers shared by the unit and integration tests
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static TEMP_DIRS: AtomicUsize = AtomicUsize::new(0);
+
+/// Temporary directory removed when dropped (even if the test fails). Names are unique per
+/// test run and per directory, so tests running in parallel never share files.
+#[derive(Debug)]
+pub struct TempDir {
+    path: PathBuf,
+}
+
+impl TempDir {
+
+    pub fn new(name: &str) -> TempDir {
+        let path = std::env::temp_dir().join(format!(
+            "game-tracker-{}-{}-{}", name, std::process::id(), TEMP_DIRS.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::create_dir_all(&path).expect("temporary directory created");
+
+        TempDir { path }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Write `content` to `relative_path` (parent directories are created)
+    pub fn write(&self, relative_path: &str, content: &str) -> PathBuf {
+        let path = self.path.join(relative_path);
+        fs::create_dir_all(path.parent().expect("file in the directory")).expect("directory created");
+        fs::write(&path, content).expect("file written");
+
+        path
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
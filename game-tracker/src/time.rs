@@ -1,9 +1,12 @@
+use std::fmt;
 use std::str::FromStr;
-use chrono::Duration;
-use regex::{CaptureMatches, Matches, Regex, RegexSet};
+use chrono::{Duration, Weekday};
+use regex::{Regex, RegexSet};
+use serde::Deserialize;
 use crate::errors::Error;
 
-#[derive(Debug, Default, Clone, PartialEq, PartialOrd)]
+#[derive(Debug, Default, Clone, PartialEq, PartialOrd, Deserialize)]
+#[serde(try_from = "String")]
 pub struct DurationParser {
     hours: i64,
     minutes: i64,
@@ -63,7 +66,6 @@ impl FromStr for DurationParser {
         }
 
         for i in re.matches(s) {
-            let current_re = re.patterns()[i].clone();
             match i {
                 0 => parse_hms_duration(s, &mut session_duration)?,
                 1 => parse_colon_duration(s, &mut session_duration)?,
@@ -74,22 +76,73 @@ impl FromStr for DurationParser {
     }
 }
 
+impl TryFrom<String> for DurationParser {
+    type Error = Error;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        DurationParser::from_str(&value)
+    }
+}
+
 impl DurationParser {
 
     pub fn to_seconds(&self) -> i64 {
         (self.hours * 60 * 60) + (self.minutes * 60) + self.seconds
     }
 
-    pub fn to_string(&self) -> String {
-        format_duration(&self.to_duration())
-    }
-
     pub fn to_duration(&self) -> Duration {
         Duration::seconds(self.to_seconds())
     }
 
 }
 
+impl fmt::Display for DurationParser {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", format_duration(&self.to_duration()))
+    }
+}
+
+/// First day of the week of weekly budgets (ex.: "monday", "sun")
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct WeekStart(Weekday);
+
+impl WeekStart {
+    pub fn weekday(&self) -> Weekday {
+        self.0
+    }
+}
+
+impl PartialOrd for WeekStart {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        self.0.num_days_from_monday().partial_cmp(&other.0.num_days_from_monday())
+    }
+}
+
+impl Default for WeekStart {
+    fn default() -> Self {
+        WeekStart(Weekday::Mon)
+    }
+}
+
+impl FromStr for WeekStart {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Weekday::from_str(s.trim())
+            .map(WeekStart)
+            .map_err(|_| Error::WeekdayParserError)
+    }
+}
+
+impl TryFrom<String> for WeekStart {
+    type Error = Error;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        WeekStart::from_str(&value)
+    }
+}
+
 pub fn format_duration(duration: &Duration) -> String {
     let days = duration.num_days();
     let hours = duration.num_hours() - (24 * duration.num_days());
@@ -1,29 +1,39 @@
-use std::collections::{BTreeMap, HashSet};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
 use std::env::home_dir;
-use std::{fs};
+use std::{fmt, fs};
 use std::fs::DirEntry;
-use std::io::Read;
-use std::path::PathBuf;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use chrono::NaiveDate;
 use serde::{Deserialize, Serialize};
-use sysinfo::{ProcessRefreshKind, RefreshKind, System, UpdateKind};
+use serde::de::DeserializeOwned;
+use rusqlite::{Connection, OpenFlags};
+use sysinfo::Pid;
+use crate::clock::{SharedClock, SystemClock};
+use crate::config::Config;
 use crate::errors::Error;
 use tampering_profiler::check_tampering;
 use crate::process_tree::{ProcessInfo, ProcessTree};
-use crate::session::DailyGamingSession;
+use crate::desktop_entry::DesktopEntry;
+use crate::game_index::GameIndex;
+use crate::process_source::ProcessSource;
+use crate::matchers::{Matcher, MatcherConfig, Precision, Rule};
+use crate::notifier::{DesktopNotifier, SharedNotifier};
+use crate::session::{end_of_local_day, DailyGamingSession};
+use crate::time::DurationParser;
+use crate::vdf;
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[allow(clippy::upper_case_acronyms)]
 pub enum ExpectedEntityType {
     EXECUTABLE,
     DIRECTORY,
+    #[default]
     BOTH
 }
 
-impl Default for ExpectedEntityType {
-    fn default() -> Self {
-        ExpectedEntityType::BOTH
-    }
-}
-
 impl ExpectedEntityType {
     fn matches(&self, entry: &DirEntry) -> bool {
         match self {
@@ -34,6 +44,196 @@ impl ExpectedEntityType {
     }
 }
 
+/// How a GameLocator finds games in its paths
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[allow(clippy::upper_case_acronyms)]
+pub enum LocatorType {
+    /// Every entry (matching `search_entity_type`) is a game
+    #[default]
+    FILESYSTEM,
+
+    /// Paths are Steam installations - games are read from
+    /// libraryfolders.vdf and appmanifest_*.acf files
+    STEAM,
+
+    /// Paths are Heroic Games Launcher configuration directories - games are read
+    /// from the Legendary (Epic), GOG and Nile (Amazon) stores
+    HEROIC,
+
+    /// Paths are Lutris databases (pga.db) - games are read from the `games` table
+    LUTRIS,
+
+    /// Paths are application directories - games are .desktop files categorised as `Game`
+    /// (XDG_DATA_HOME and XDG_DATA_DIRS application directories are always searched)
+    DESKTOP,
+}
+
+/// Game represents an installed game found by a GameLocator.
+/// Games are identified by their name and their (optional) platform id.
+#[derive(Debug, Clone)]
+pub struct Game {
+    id: Option<String>,
+    name: String,
+    install_dir: Option<PathBuf>,
+    executable: Option<PathBuf>,
+    category: Option<String>,
+    limit: Option<chrono::Duration>,
+    matcher: Matcher,
+}
+impl PartialEq<Self> for Game {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name && self.id == other.id
+    }
+}
+
+impl Eq for Game {}
+
+impl PartialOrd<Self> for Game {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Game {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.name.cmp(&other.name)
+            .then_with(|| self.id.cmp(&other.id))
+    }
+}
+
+impl Hash for Game {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.name.hash(state);
+        self.id.hash(state);
+    }
+}
+
+impl fmt::Display for Game {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+impl Game {
+
+    /// Game found by name only (the name is searched in processes)
+    pub fn from_name(name: &str) -> Self {
+        let mut matcher = Matcher::new();
+        matcher.add(Rule::Substring(name.to_string()));
+
+        Game {
+            id: None,
+            name: name.to_string(),
+            install_dir: None,
+            executable: None,
+            category: None,
+            limit: None,
+            matcher,
+        }
+    }
+
+    /// Game installed in `install_dir` (processes running from it are the game's processes).
+    /// The directory name is only searched in command lines if the platform enables `substring_match`.
+    pub fn installed_in(id: Option<&str>, name: &str, install_dir: &Path) -> Self {
+        let mut matcher = Matcher::new();
+        matcher.add(Rule::InstallDir(install_dir.to_path_buf()));
+        matcher.add(Rule::Substring(
+            install_dir.file_name()
+                .map(|dir| dir.to_string_lossy().to_string())
+                .unwrap_or_else(|| name.to_string())
+        ));
+        matcher.set_substring_default(false);
+
+        Game {
+            id: id.map(str::to_string),
+            name: name.to_string(),
+            install_dir: Some(install_dir.to_path_buf()),
+            executable: None,
+            category: None,
+            limit: None,
+            matcher,
+        }
+    }
+
+    /// Set the game's executable (processes named like it are the game's processes).
+    /// The name is then only searched in command lines if the platform enables `substring_match`.
+    pub fn with_executable(mut self, executable: PathBuf) -> Self {
+        self.matcher.set_substring_default(false);
+
+        if executable.is_absolute() {
+            self.matcher.add(Rule::Executable(executable.clone()));
+        }
+
+        if let Some(file_name) = executable.file_name() {
+            self.matcher.add(Rule::ProcessName(file_name.to_string_lossy().to_string()));
+        }
+
+        self.executable = Some(executable);
+        self
+    }
+
+    pub fn id(&self) -> Option<&str> { self.id.as_deref() }
+
+    pub fn name(&self) -> &str { &self.name }
+
+    pub fn category(&self) -> Option<&str> { self.category.as_deref() }
+
+    pub fn matcher(&self) -> &Matcher { &self.matcher }
+
+    /// Maximum play time of the game (independent of the gaming session)
+    pub fn limit(&self) -> Option<chrono::Duration> { self.limit }
+
+    /// Checks if both games are the same game (same name or same id)
+    pub fn conflicts_with(&self, other: &Game) -> bool {
+        self.name.eq_ignore_ascii_case(&other.name)
+            || (self.id.is_some() && self.id == other.id)
+    }
+
+    /// Search the process `pid` (and its children) for this game
+    pub fn find<'a>(&self, tree: &'a ProcessTree, pid: Pid, precision: Precision) -> Option<&'a ProcessInfo> {
+        self.matcher.find(tree, pid, precision)
+            .map(|(process, _)| process)
+    }
+}
+
+
+/// Game declared in the configuration file (`[[games]]`) - used for games no locator finds
+#[derive(Deserialize, Clone, Debug)]
+pub struct ManualGame {
+
+    /// Display name
+    name: String,
+
+    /// Category (ex.: emulator, sandbox, ...)
+    #[serde(default)]
+    category: Option<String>,
+
+    /// Maximum play time of this game (ex.: "1h30m")
+    #[serde(default)]
+    limit: Option<DurationParser>,
+
+    /// Rules used to recognize the game's processes (the name is searched if there are none)
+    #[serde(flatten)]
+    matchers: MatcherConfig,
+}
+
+impl ManualGame {
+    pub fn to_game(&self) -> Result<Game, Error> {
+        let matcher = self.matchers.compile()?;
+        let mut game = Game::from_name(&self.name);
+
+        if matcher.has_rules() {
+            // configured rules replace the name search
+            game.matcher = matcher;
+        } else {
+            game.matcher.merge(&matcher);
+        }
+
+        game.category = self.category.clone();
+        game.limit = self.limit.as_ref().map(DurationParser::to_duration);
+        Ok(game)
+    }
+}
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct GameLocator {
@@ -42,9 +242,9 @@ pub struct GameLocator {
     #[serde(skip)]
     name: String,
 
-    /// name of games found!
+    /// games found!
     #[serde(skip)]
-    games: Vec<String>,
+    games: Vec<Game>,
 
     /// Paths to search in home directory
     #[serde(default)]
@@ -54,16 +254,41 @@ pub struct GameLocator {
     #[serde(default)]
     absolute_paths: Vec<PathBuf>,
 
+    /// Type of locator used to find games in paths
+    #[serde(default)]
+    locator_type: LocatorType,
+
     /// Games in directory will be a EntityType (directory, file or both)
     #[serde(default)]
     search_entity_type: ExpectedEntityType,
 
+    /// Flatpak application id(s) of the platform (ex.: com.valvesoftware.Steam)
+    #[serde(default)]
+    flatpak_ids: Vec<String>,
+
+    /// Flatpak application ids of every platform (desktop entries launching them are
+    /// launchers, not games)
+    #[serde(skip)]
+    launcher_flatpak_ids: Vec<String>,
+
+    /// Snap package name(s) of the platform
+    #[serde(default)]
+    snap_names: Vec<String>,
+
+    /// Matching rules added to every game of the platform
+    #[serde(default)]
+    matchers: MatcherConfig,
+
+    /// Matching rules added to a single game (key is the game's name or id)
+    #[serde(default)]
+    game_matchers: BTreeMap<String, MatcherConfig>,
+
     /// Ignore list
     #[serde(default)]
     ignore: Vec<String>,
 }
 
-fn should_be_ignored(name: &String, ignore: &Vec<String>) -> bool {
+fn should_be_ignored(name: &str, ignore: &[String]) -> bool {
     for ig in ignore {
         if name.starts_with(ig) {
             return true;
@@ -73,11 +298,507 @@ fn should_be_ignored(name: &String, ignore: &Vec<String>) -> bool {
     false
 }
 
+fn read_vdf(path: &Path) -> Option<vdf::VdfValue> {
+    let content = fs::read_to_string(path).ok()?;
+
+    match vdf::parse(&content) {
+        Ok(document) => Some(document),
+        Err(e) => {
+            println!("Could not parse {:?} : {}", path, e);
+            None
+        }
+    }
+}
+
+/// Find every Steam library known by a Steam installation (the installation
+/// itself is always a library, other libraries are listed in libraryfolders.vdf)
+fn steam_libraries(steam_root: &Path) -> Vec<PathBuf> {
+    let mut libraries = vec![steam_root.to_path_buf()];
+
+    let library_folders = ["steamapps/libraryfolders.vdf", "config/libraryfolders.vdf"]
+        .iter()
+        .find_map(|p| read_vdf(&steam_root.join(p)));
+
+    if let Some(document) = library_folders {
+        let folders = document.entries().iter()
+            .flat_map(|(_, root)| root.entries());
+
+        for (_, folder) in folders {
+            // old format : "1" "/path/to/library"
+            // new format : "1" { "path" "/path/to/library" ... }
+            let path = match folder {
+                vdf::VdfValue::String(path) => Some(path.as_str()),
+                vdf::VdfValue::Object(_) => folder.get_str("path"),
+            };
+
+            if let Some(path) = path && Path::new(path).is_absolute() {
+                libraries.push(PathBuf::from(path));
+            }
+        }
+    }
+
+    libraries
+}
+
+/// Read every appmanifest_*.acf of a Steam library
+fn steam_library_games(library: &Path) -> Vec<Game> {
+    let steamapps = library.join("steamapps");
+    let mut games = vec![];
+
+    let Ok(entries) = fs::read_dir(&steamapps) else {
+        return games;
+    };
+
+    for entry in entries.filter_map(Result::ok) {
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        if !file_name.starts_with("appmanifest_") || !file_name.ends_with(".acf") {
+            continue;
+        }
+
+        let Some(document) = read_vdf(&entry.path()) else {
+            continue;
+        };
+
+        let Some(app_state) = document.get("AppState") else {
+            continue;
+        };
+
+        if let (Some(id), Some(name), Some(install_dir)) = (
+            app_state.get_str("appid"),
+            app_state.get_str("name"),
+            app_state.get_str("installdir"),
+        ) {
+            let mut game = Game::installed_in(
+                Some(id), name, &steamapps.join("common").join(install_dir)
+            );
+
+            game.matcher.add(Rule::SteamAppId(id.to_string()));
+            games.push(game);
+        }
+    }
+
+    games
+}
+
+fn read_json<T: DeserializeOwned>(path: &Path) -> Option<T> {
+    let content = fs::read(path).ok()?;
+
+    match serde_json::from_slice(&content) {
+        Ok(document) => Some(document),
+        Err(e) => {
+            println!("Could not parse {:?} : {}", path, e);
+            None
+        }
+    }
+}
+
+/// Entry of legendaryConfig/legendary/installed.json (Epic Games)
+#[derive(Deserialize, Debug)]
+struct LegendaryInstalledGame {
+    app_name: String,
+    title: String,
+    install_path: PathBuf,
+    #[serde(default)]
+    executable: String,
+}
+
+/// Entry of gog_store/installed.json
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct GogInstalledGame {
+    app_name: String,
+    #[serde(rename = "install_path")]
+    install_path: PathBuf,
+    #[serde(default)]
+    executable: String,
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct GogInstalled {
+    #[serde(default)]
+    installed: Vec<GogInstalledGame>,
+}
+
+/// Entry of gog_store/library.json (only used to get titles)
+#[derive(Deserialize, Debug)]
+struct GogLibraryGame {
+    app_name: String,
+    title: String,
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct GogLibrary {
+    #[serde(default)]
+    games: Vec<GogLibraryGame>,
+}
+
+/// Entry of nile_config/nile/installed.json (Amazon Games)
+#[derive(Deserialize, Debug)]
+struct NileInstalledGame {
+    id: String,
+    path: PathBuf,
+}
+
+/// Entry of nile_config/nile/library.json (only used to get titles)
+#[derive(Deserialize, Debug)]
+struct NileLibraryGame {
+    id: String,
+    product: NileProduct,
+}
+
+#[derive(Deserialize, Debug)]
+struct NileProduct {
+    title: String,
+}
+
+/// Title of a game whose store doesn't know its title (uses the installation directory)
+fn title_from_install_path(install_path: &Path) -> String {
+    install_path.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| install_path.to_string_lossy().to_string())
+}
+
+/// Read every game installed through Heroic (`heroic_config` is Heroic's configuration directory)
+fn heroic_games(heroic_config: &Path) -> Vec<Game> {
+    let mut games = vec![];
+
+    let legendary: BTreeMap<String, LegendaryInstalledGame> =
+        read_json(&heroic_config.join("legendaryConfig/legendary/installed.json"))
+            .unwrap_or_default();
+
+    for (_, installed) in legendary {
+        let mut game = Game::installed_in(
+            Some(&installed.app_name), &installed.title, &installed.install_path
+        );
+
+        if !installed.executable.is_empty() {
+            game = game.with_executable(installed.install_path.join(&installed.executable));
+        }
+
+        games.push(game);
+    }
+
+    let gog_titles: BTreeMap<String, String> =
+        read_json::<GogLibrary>(&heroic_config.join("gog_store/library.json"))
+            .unwrap_or_default()
+            .games.into_iter()
+            .map(|g| (g.app_name, g.title))
+            .collect();
+
+    let gog: GogInstalled = read_json(&heroic_config.join("gog_store/installed.json"))
+        .unwrap_or_default();
+
+    for installed in gog.installed {
+        let title = gog_titles.get(&installed.app_name).cloned()
+            .unwrap_or_else(|| title_from_install_path(&installed.install_path));
+
+        let mut game = Game::installed_in(
+            Some(&installed.app_name), &title, &installed.install_path
+        );
+
+        if !installed.executable.is_empty() {
+            game = game.with_executable(installed.install_path.join(&installed.executable));
+        }
+
+        games.push(game);
+    }
+
+    let nile_titles: BTreeMap<String, String> =
+        read_json::<Vec<NileLibraryGame>>(&heroic_config.join("nile_config/nile/library.json"))
+            .unwrap_or_default()
+            .into_iter()
+            .map(|g| (g.id, g.product.title))
+            .collect();
+
+    let nile: Vec<NileInstalledGame> = read_json(&heroic_config.join("nile_config/nile/installed.json"))
+        .unwrap_or_default();
+
+    for installed in nile {
+        let title = nile_titles.get(&installed.id).cloned()
+            .unwrap_or_else(|| title_from_install_path(&installed.path));
+
+        games.push(Game::installed_in(Some(&installed.id), &title, &installed.path));
+    }
+
+    games
+}
+
+/// Read every installed game of a Lutris database (pga.db)
+fn lutris_games(database: &Path) -> Result<Vec<Game>, Error> {
+    let conn = Connection::open_with_flags(
+        database,
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX
+    )?;
+
+    let mut statement = conn.prepare("
+        SELECT slug, name, runner, directory
+        FROM games
+        WHERE installed = 1
+    ")?;
+
+    let rows = statement.query_map((), |row| Ok((
+        row.get::<_, String>(0)?,
+        row.get::<_, String>(1)?,
+        row.get::<_, Option<String>>(2)?,
+        row.get::<_, Option<String>>(3)?,
+    )))?;
+
+    let mut games = vec![];
+    for row in rows {
+        let (slug, name, runner, directory) = row?;
+
+        // Steam games are launched by Steam (and found by the Steam locator)
+        if runner.as_deref() == Some("steam") {
+            continue;
+        }
+
+        match directory.filter(|d| !d.is_empty()) {
+            Some(directory) => games.push(Game::installed_in(Some(&slug), &name, Path::new(&directory))),
+            None => println!("Lutris game '{}' has no install directory - it cannot be tracked", name),
+        }
+    }
+
+    Ok(games)
+}
+
+/// Flatpak applications get their own XDG directories inside ~/.var/app/<id>
+const FLATPAK_XDG_DIRECTORIES: [(&str, &str); 3] = [
+    (".config", "config"),
+    (".local/share", "data"),
+    (".cache", "cache"),
+];
+
+/// Sandbox maps paths seen by sandboxed processes (Flatpak) to paths on the host.
+/// (ex.: ~/.local/share/Steam inside the sandbox is ~/.var/app/com.valvesoftware.Steam/.local/share/Steam)
+#[derive(Debug, Clone)]
+struct Sandbox {
+    host_root: PathBuf,
+    sandbox_root: PathBuf,
+}
+
+impl Sandbox {
+
+    /// Translate a path written by a sandboxed application to a host path
+    /// (paths that only exist outside the sandbox are left untouched)
+    fn to_host(&self, p: &Path) -> PathBuf {
+        match p.strip_prefix(&self.sandbox_root) {
+            Ok(relative) if self.host_root.join(relative).exists() => self.host_root.join(relative),
+            _ => p.to_path_buf()
+        }
+    }
+
+    /// Translate a host path to the path seen by sandboxed processes
+    fn to_sandbox(&self, p: &Path) -> Option<PathBuf> {
+        p.strip_prefix(&self.host_root).ok()
+            .map(|relative| self.sandbox_root.join(relative))
+    }
+}
+
+/// Path searched by a GameLocator (and the sandbox it belongs to)
+#[derive(Debug, Clone)]
+struct SearchPath {
+    path: PathBuf,
+    sandbox: Option<Sandbox>,
+}
+
+impl SearchPath {
+    fn host(path: PathBuf) -> Self {
+        SearchPath { path, sandbox: None }
+    }
+}
+
+/// Paths where a Flatpak application stores `home_path` (relative to the home directory)
+fn flatpak_search_paths(home: &Path, flatpak_id: &str, home_path: &Path) -> Vec<SearchPath> {
+    let app_root = home.join(".var/app").join(flatpak_id);
+
+    // persisted directories are mounted in the sandbox at the same place they'd be on the host
+    let mut paths = vec![SearchPath {
+        path: app_root.join(home_path),
+        sandbox: Some(Sandbox { host_root: app_root.clone(), sandbox_root: home.to_path_buf() }),
+    }];
+
+    // XDG directories are visible at the same path inside and outside the sandbox
+    for (xdg_directory, flatpak_directory) in FLATPAK_XDG_DIRECTORIES {
+        if let Ok(relative) = home_path.strip_prefix(xdg_directory) {
+            paths.push(SearchPath::host(app_root.join(flatpak_directory).join(relative)));
+        }
+    }
+
+    paths
+}
+
+/// Paths where a Snap package stores `home_path` (snaps get their own home directory)
+fn snap_search_paths(home: &Path, snap_name: &str, home_path: &Path) -> Vec<SearchPath> {
+    ["current", "common"].iter()
+        .map(|revision| SearchPath::host(home.join("snap").join(snap_name).join(revision).join(home_path)))
+        .collect()
+}
+
+/// Programs launching other applications - they can't be used to recognize a game
+const GENERIC_LAUNCHERS: [&str; 11] = [
+    "env", "sh", "bash", "flatpak", "snap", "xdg-open", "gtk-launch",
+    "steam", "lutris", "heroic", "wine",
+];
+
+/// Application directories of the freedesktop base directory specification
+fn xdg_application_directories() -> Vec<PathBuf> {
+    let mut directories = vec![];
+
+    match std::env::var_os("XDG_DATA_HOME").filter(|d| !d.is_empty()) {
+        Some(data_home) => directories.push(PathBuf::from(data_home)),
+        None => if let Some(home) = home_dir() {
+            directories.push(home.join(".local/share"));
+        }
+    }
+
+    let data_dirs = std::env::var("XDG_DATA_DIRS").ok()
+        .filter(|d| !d.is_empty())
+        .unwrap_or_else(|| "/usr/local/share:/usr/share".to_string());
+
+    directories.extend(data_dirs.split(':').filter(|d| !d.is_empty()).map(PathBuf::from));
+    directories.into_iter()
+        .map(|d| d.join("applications"))
+        .collect()
+}
+
+/// Read every .desktop file categorised as a game in an application directory (and its subdirectories).
+/// The desktop file id (ex.: org.supertuxproject.SuperTux) is used as the game id.
+/// Entries running one of the `launchers` Flatpak applications are skipped.
+fn desktop_games(applications: &Path, prefix: &str, launchers: &[String], games: &mut Vec<Game>) {
+    let Ok(entries) = fs::read_dir(applications) else {
+        return;
+    };
+
+    for entry in entries.filter_map(Result::ok) {
+        let path = entry.path();
+        let file_name = entry.file_name().to_string_lossy().to_string();
+
+        if path.is_dir() {
+            desktop_games(&path, &format!("{}{}-", prefix, file_name), launchers, games);
+            continue;
+        }
+
+        let Some(desktop_id) = file_name.strip_suffix(".desktop") else {
+            continue;
+        };
+
+        let Some(desktop_entry) = fs::read_to_string(&path).ok()
+            .and_then(|content| DesktopEntry::parse(&content)) else {
+            continue;
+        };
+
+        if !desktop_entry.is_game() {
+            continue;
+        }
+
+        // ex.: flatpak run --command=/app/bin/steam-wrapper com.valvesoftware.Steam
+        if desktop_entry.flatpak_app_id().is_some_and(|app_id| launchers.contains(&app_id)) {
+            continue;
+        }
+
+        let programs = [desktop_entry.try_exec().map(str::to_string), desktop_entry.exec_program()];
+        let mut game = Game::from_name(desktop_entry.name());
+        game.id = Some(format!("{}{}", prefix, desktop_id));
+
+        for program in programs.into_iter().flatten() {
+            let program = PathBuf::from(program);
+            let is_launcher = program.file_name()
+                .is_some_and(|name| GENERIC_LAUNCHERS.iter().any(|launcher| name == *launcher));
+
+            if !is_launcher {
+                game = game.with_executable(program);
+            }
+        }
+
+        // games launched through another launcher (ex.: steam steam://rungameid/620) are found by their own locator
+        if game.executable.is_some() {
+            games.push(game);
+        }
+    }
+}
+
 impl GameLocator {
-    fn load(&mut self) {
+    fn load(&mut self) -> Result<(), Error> {
+        let search_paths: Vec<SearchPath> = self.search_paths().into_iter()
+            .filter(|p| p.path.exists())
+            .collect();
+
+        match self.locator_type {
+            LocatorType::FILESYSTEM => search_paths.iter()
+                .for_each(|p| self.load_game_names_from_path(p)),
+            LocatorType::STEAM => self.load_steam_games(&search_paths),
+            LocatorType::HEROIC => self.load_heroic_games(&search_paths),
+            LocatorType::LUTRIS => self.load_lutris_games(&search_paths),
+            LocatorType::DESKTOP => self.load_desktop_games(&search_paths),
+        }
+
+        self.configure_matchers()
+    }
+
+    pub fn games(&self) -> &[Game] {
+        &self.games
+    }
+
+    /// Configured paths that don't exist (a home path exists if one of its sandboxed variants exists)
+    pub fn missing_paths(&self) -> Vec<PathBuf> {
+        let mut missing = vec![];
+
+        if let Some(home) = home_dir() {
+            for home_path in self.home_paths.iter() {
+                if !self.home_path_candidates(&home, home_path).iter().any(|p| p.path.exists()) {
+                    missing.push(home.join(home_path));
+                }
+            }
+        }
+
+        missing.extend(self.absolute_paths.iter().filter(|p| !p.exists()).cloned());
+        missing
+    }
+
+    /// Add the configured matching rules to the games found
+    fn configure_matchers(&mut self) -> Result<(), Error> {
+        let platform_matcher = self.matchers.compile()?;
+        let mut game_matchers = BTreeMap::new();
+        for (key, config) in self.game_matchers.iter() {
+            game_matchers.insert(key.as_str(), config.compile()?);
+        }
+
+        for game in self.games.iter_mut() {
+            game.matcher.merge(&platform_matcher);
+
+            let game_matcher = game_matchers.get(game.name.as_str())
+                .or_else(|| game.id.as_deref().and_then(|id| game_matchers.get(id)));
+
+            if let Some(game_matcher) = game_matcher {
+                game.matcher.merge(game_matcher);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Paths where `home_path` can be found (on the host and in Flatpak/Snap sandboxes)
+    fn home_path_candidates(&self, home: &Path, home_path: &Path) -> Vec<SearchPath> {
+        let mut paths = vec![SearchPath::host(home.join(home_path))];
+
+        for flatpak_id in self.flatpak_ids.iter() {
+            paths.extend(flatpak_search_paths(home, flatpak_id, home_path));
+        }
+
+        for snap_name in self.snap_names.iter() {
+            paths.extend(snap_search_paths(home, snap_name, home_path));
+        }
+
+        paths
+    }
+
+    fn search_paths(&self) -> Vec<SearchPath> {
+        let mut paths = vec![];
         match home_dir() {
             Some(home) => {
-                self.load_home_game_names(&home)
+                for home_path in self.home_paths.iter() {
+                    paths.extend(self.home_path_candidates(&home, home_path));
+                }
             },
             None => println!(
                 "Could not find home directory - cannot load {:?} home paths...",
@@ -85,166 +806,670 @@ impl GameLocator {
             ),
         }
 
-        self.absolute_paths.clone().iter()
-            .for_each(|p| self.load_game_names_from_path(p));
+        paths.extend(self.absolute_paths.iter().cloned().map(SearchPath::host));
+        if let LocatorType::DESKTOP = self.locator_type {
+            paths.extend(xdg_application_directories().into_iter().map(SearchPath::host));
+        }
+
+        paths
     }
 
-    fn load_game_names_from_path(&mut self, p: &PathBuf) {
-        if let Ok(directories) = fs::read_dir(p) {
-            for entry in directories.filter_map(Result::ok).filter(|d| self.search_entity_type.matches(&d)) {
+    fn load_game_names_from_path(&mut self, p: &SearchPath) {
+        if let Ok(directories) = fs::read_dir(&p.path) {
+            let entries: Vec<DirEntry> = directories.filter_map(Result::ok)
+                .filter(|d| self.search_entity_type.matches(d))
+                .collect();
+
+            for entry in entries {
                 let file_name = entry.file_name().to_string_lossy().to_string();
                 if !should_be_ignored(&file_name, &self.ignore) {
-                    self.games.push(file_name);
+                    let game = if entry.path().is_dir() {
+                        Game::installed_in(None, &file_name, &entry.path())
+                    } else {
+                        Game::from_name(&file_name).with_executable(entry.path())
+                    };
+
+                    self.add_game(game, p);
                 }
             }
         }
     }
 
-    fn load_home_game_names(&mut self, base_path: &PathBuf) {
-        self.home_paths.clone().iter().for_each(|p| {
-            self.load_game_names_from_path(&base_path.join(p))
-        });
+    fn load_steam_games(&mut self, steam_roots: &[SearchPath]) {
+        let mut libraries: Vec<(PathBuf, &SearchPath)> = vec![];
+
+        // the same library can be listed by multiple installations (ex.: ~/.steam/steam symlink)
+        for root in steam_roots {
+            for library in steam_libraries(&root.path) {
+                let library = match root.sandbox.as_ref() {
+                    Some(sandbox) => sandbox.to_host(&library),
+                    None => library,
+                };
+
+                let library = library.canonicalize().unwrap_or(library);
+                if !libraries.iter().any(|(known, _)| *known == library) {
+                    libraries.push((library, root));
+                }
+            }
+        }
+
+        for (library, root) in libraries {
+            for game in steam_library_games(&library) {
+                self.add_game(game, root);
+            }
+        }
     }
 
-}
+    fn load_heroic_games(&mut self, heroic_configs: &[SearchPath]) {
+        for config in heroic_configs {
+            for game in heroic_games(&config.path) {
+                self.add_game(game, config);
+            }
+        }
+    }
+
+    fn load_lutris_games(&mut self, databases: &[SearchPath]) {
+        for database in databases.iter().filter(|db| db.path.is_file()) {
+            match lutris_games(&database.path) {
+                Ok(games) => games.into_iter().for_each(|game| self.add_game(game, database)),
+                Err(e) => println!("Could not read Lutris database {:?} : {}", database.path, e),
+            }
+        }
+    }
+
+    fn load_desktop_games(&mut self, application_directories: &[SearchPath]) {
+        let mut known_ids: HashSet<String> = HashSet::new();
 
-type Games = BTreeMap<String, GameLocator>;
+        for directory in application_directories {
+            let mut games = vec![];
+            desktop_games(&directory.path, "", &self.launcher_flatpak_ids, &mut games);
 
-fn find_game<'a>(p: &'a ProcessInfo, games: &Games) -> Option<(String, &'a ProcessInfo)> {
-    for (_, platform) in games.iter() {
-        for name in platform.games.iter() {
-            if let Some(process) = p.find(name) {
-                return Some((name.clone(), process));
+            // the first directory defining a desktop file id has priority (ex.: ~/.local/share over /usr/share)
+            for game in games {
+                if known_ids.insert(game.id.clone().unwrap_or_default()) {
+                    self.add_game(game, directory);
+                }
             }
         }
     }
 
-    None
+    fn add_game(&mut self, mut game: Game, found_in: &SearchPath) {
+        if should_be_ignored(&game.name, &self.ignore) || self.games.contains(&game) {
+            return;
+        }
+
+        // sandboxed processes see the game's files at a different path
+        if let Some(sandbox) = found_in.sandbox.as_ref()
+            && let Some(install_dir) = game.install_dir.as_deref()
+            && let Some(alias) = sandbox.to_sandbox(install_dir) {
+            game.matcher.add(Rule::InstallDir(alias));
+        }
+
+        self.games.push(game);
+    }
+
+}
+
+pub type Games = BTreeMap<String, GameLocator>;
+
+fn all_games<'a>(manual_games: &'a [Game], games: &'a Games) -> impl Iterator<Item = &'a Game> {
+    manual_games.iter()
+        .chain(games.values().flat_map(|platform| platform.games.iter()))
+}
+
+/// Time played of a game and its processes found by the last scan. Time played is
+/// wall-clock time : a game running with a launcher and 5 other processes for 10 minutes
+/// was played 10 minutes.
+#[derive(Debug, Default, Clone)]
+pub struct GameTime {
+    processes: HashSet<ProcessInfo>,
+    time_played: chrono::Duration,
+}
+
+impl GameTime {
+
+    /// Processes of the game found by the last scan (empty once the game exits)
+    pub fn processes(&self) -> &HashSet<ProcessInfo> {
+        &self.processes
+    }
+
+    pub fn is_running(&self) -> bool {
+        !self.processes.is_empty()
+    }
+
+    pub fn time_played(&self) -> chrono::Duration {
+        self.time_played
+    }
+}
+
+/// Time played on a day that ended during the last scan : the tracker's totals are reset
+/// for the new day, the statistics save these ones first
+#[derive(Debug, Clone)]
+pub struct PlayedDay {
+    day: NaiveDate,
+    time_played: chrono::Duration,
+    games: Vec<(Game, chrono::Duration)>,
+}
+
+impl PlayedDay {
+
+    pub fn day(&self) -> NaiveDate {
+        self.day
+    }
+
+    /// Time at least one game was running that day
+    pub fn time_played(&self) -> chrono::Duration {
+        self.time_played
+    }
+
+    /// Time played of each game played that day
+    pub fn games(&self) -> &[(Game, chrono::Duration)] {
+        &self.games
+    }
 }
 
 #[derive(Debug)]
 pub struct GamingTracker {
-    system_processes: System,
+    process_source: Box<dyn ProcessSource>,
+    clock: SharedClock,
+    notifier: SharedNotifier,
     installed_games: Games,
+    manual_games: Vec<Game>,
+    game_index: GameIndex,
     process_snapshots: ProcessTree,
-    games: BTreeMap<String, HashSet<ProcessInfo>>,
+    games: BTreeMap<Game, GameTime>,
+    total_time_played: chrono::Duration,
+    last_scan: Instant,
+    day: NaiveDate,
+    ended_day: Option<PlayedDay>,
     gaming_session: Option<DailyGamingSession>
 }
 
 impl GamingTracker {
 
-    pub fn new() -> Self {
+    pub fn with_source(process_source: Box<dyn ProcessSource>, clock: SharedClock) -> Self {
         GamingTracker {
-            system_processes:
-            System::new_with_specifics(
-                RefreshKind::nothing()
-                    .with_processes(
-                        ProcessRefreshKind::nothing()
-                            .with_cmd(UpdateKind::OnlyIfNotSet)
-                            .with_cwd(UpdateKind::OnlyIfNotSet)
-                            .with_exe(UpdateKind::OnlyIfNotSet)
-                            .with_user(UpdateKind::OnlyIfNotSet)
-            )),
+            process_source,
+            notifier: DesktopNotifier::shared(),
             installed_games: Games::new(),
+            manual_games: vec![],
+            game_index: GameIndex::default(),
             process_snapshots: ProcessTree::new(),
             games: BTreeMap::new(),
+            total_time_played: chrono::Duration::zero(),
+            last_scan: clock.monotonic(),
+            day: clock.now().date_naive(),
+            ended_day: None,
+            clock,
             gaming_session: None
         }
     }
 
+    /// Show the tracker's messages with `notifier` (desktop notifications by default)
+    pub fn with_notifier(mut self, notifier: SharedNotifier) -> Self {
+        self.notifier = notifier;
+        self
+    }
+
+    /// Show `msg` to the player
+    pub fn notify(&self, msg: &str) -> Result<(), Error> {
+        self.notifier.notify(msg)
+    }
+
     pub fn add_gaming_session(&mut self, gaming_session: DailyGamingSession) {
         self.gaming_session = Some(gaming_session);
     }
 
-    pub fn gametime_tracker(&self) -> &BTreeMap<String, HashSet<ProcessInfo>> {
+    // Game's order only depends on its name and id (never on its matcher)
+    #[allow(clippy::mutable_key_type)]
+    pub fn gametime_tracker(&self) -> &BTreeMap<Game, GameTime> {
         &self.games
     }
 
+    /// Processes of every game found by the last scan
+    pub fn running_processes(&self) -> impl Iterator<Item = &ProcessInfo> {
+        self.games.values().flat_map(|game_time| game_time.processes.iter())
+    }
+
+    /// Time played of a single game
+    pub fn time_played(&self, game: &Game) -> chrono::Duration {
+        self.games.get(game)
+            .map(GameTime::time_played)
+            .unwrap_or_default()
+    }
+
+    /// Games played longer than their own limit
+    pub fn games_over_limit(&self) -> Vec<&Game> {
+        self.games.keys()
+            .filter(|game| game.limit().is_some_and(|limit| self.time_played(game) > limit))
+            .collect()
+    }
+
+    /// Time at least one game was running (games played at the same time are counted once)
     pub fn total_time_played(&self) -> chrono::Duration {
-        let mut total_seconds: u64 = 0;
+        self.total_time_played
+    }
+
+    /// Time already played today (restored from the statistics when the tracker starts)
+    pub fn restore_time_played(&mut self, time_played: chrono::Duration) {
+        self.total_time_played = time_played;
+    }
+
+    /// Time already played today by `game` (restored from the statistics when the tracker starts)
+    pub fn restore_game_time_played(&mut self, name: &str, id: Option<&str>, time_played: chrono::Duration) {
+        let game = self.game_index.games().iter()
+            .find(|game| game.name() == name && game.id() == id);
 
-        self.games.iter()
-            .flat_map(|(_, processes)| processes.into_iter())
-            .for_each(|proc| total_seconds += proc.run_time());
+        if let Some(game) = game {
+            self.games.entry(game.clone()).or_default().time_played = time_played;
+        }
+    }
 
-        chrono::Duration::seconds(total_seconds as i64)
+    /// Day the time played is counted for (games' time is reset at midnight)
+    pub fn day(&self) -> NaiveDate {
+        self.day
+    }
+
+    /// Time played on the day that ended during the last scan (once : it's only returned
+    /// by the first call)
+    pub fn take_ended_day(&mut self) -> Option<PlayedDay> {
+        self.ended_day.take()
     }
 
     pub fn session(&self) -> Option<&DailyGamingSession> {
         self.gaming_session.as_ref()
     }
 
-    pub fn try_from(config_path: &str) -> Result<Self, Error> {
-        let mut s = Self::new();
-        s.load_config(config_path)?;
+    pub fn session_mut(&mut self) -> Option<&mut DailyGamingSession> {
+        self.gaming_session.as_mut()
+    }
+
+    pub fn try_from(config: Config) -> Result<Self, Error> {
+        let source = config.tracker.process_source.unwrap_or_default();
+        let clock = SystemClock::shared();
+        let mut s = Self::with_source(source.create(clock.clone()), clock);
+        s.load_config(config)?;
 
         Ok(s)
     }
 
     #[check_tampering]
-    pub fn load_config(&mut self, config_path: &str) -> Result<(), Error> {
-        let mut file = fs::File::open(config_path)?;
-        let mut buffer = vec![];
-
-        file.read_to_end(buffer.as_mut())?;
-        match toml::from_slice::<Games>(&buffer) {
-            Ok(mut config) => {
-                config.iter_mut()
-                    .for_each(|(platform_name, platform)| {
-                        platform.name = platform_name.clone();
-                        platform.load();
-                });
-
-                self.installed_games = config;
-                Ok(())
-            },
-            Err(e) => Err(e.into())
+    pub fn load_config(&mut self, config: Config) -> Result<(), Error> {
+        self.rescan(config)
+    }
+
+    /// Same as `load_config` without the execution time check : rescanning large libraries
+    /// (slow disks, network shares) while the tracker runs isn't tampering.
+    pub fn rescan(&mut self, config: Config) -> Result<(), Error> {
+        let mut platforms = config.platforms;
+        let manual_games = config.games.iter()
+            .map(ManualGame::to_game)
+            .collect::<Result<Vec<Game>, Error>>()?;
+
+        let launcher_flatpak_ids: Vec<String> = platforms.values()
+            .flat_map(|platform| platform.flatpak_ids.iter().cloned())
+            .collect();
+
+        for (platform_name, platform) in platforms.iter_mut() {
+            platform.name = platform_name.clone();
+            platform.launcher_flatpak_ids = launcher_flatpak_ids.clone();
+            platform.load()?;
+
+            // games declared manually have priority over the games found
+            platform.games.retain(|game| !manual_games.iter().any(|m| m.conflicts_with(game)));
         }
+
+        // exact matches (Steam app id, install directory, ...) have priority over name searches,
+        // then games declared manually have priority over the games found
+        let game_index = GameIndex::new(all_games(&manual_games, &platforms).cloned().collect())?;
+
+        self.installed_games = platforms;
+        self.manual_games = manual_games;
+        self.game_index = game_index;
+        Ok(())
+    }
+
+    pub fn platforms(&self) -> &Games {
+        &self.installed_games
+    }
+
+    pub fn manual_games(&self) -> &[Game] {
+        &self.manual_games
+    }
+
+    pub fn process_tree(&self) -> &ProcessTree {
+        &self.process_snapshots
+    }
+
+    /// Games running in the last scanned processes, with the process (and rule) the tracking
+    /// counts for each of them
+    pub fn running_games(&self) -> Vec<(&Game, &ProcessInfo, &Rule)> {
+        self.game_index.find_all(&self.process_snapshots)
+    }
+
+    /// Game (and rule) matching this process - unlike the tracking, children aren't searched
+    pub fn matching_game(&self, p: &ProcessInfo) -> Option<(&Game, &Rule)> {
+        self.game_index.matching_game(p)
+    }
+
+    /// Names of every game that can be tracked (found by locators or declared manually)
+    pub fn installed_game_names(&self) -> BTreeSet<String> {
+        self.game_index.games().iter()
+            .map(|game| game.name().to_string())
+            .collect()
     }
 
     #[check_tampering]
     pub fn refresh(&mut self) -> Result<(), Error> {
-        self.system_processes.refresh_all();
-        self.process_snapshots = ProcessTree::from(self.system_processes.processes());
-        self.update_running_games();
-
-        let time_played = self.total_time_played();
-        if let Some(time_played_tracker) = self.gaming_session.as_mut() {
-            if time_played_tracker.is_passed_midnight() {
-                time_played_tracker.restart_session()?;
-            }
+        self.process_snapshots = self.process_source.refresh()?;
+
+        let now = self.clock.monotonic();
+        let elapsed = chrono::Duration::from_std(now.saturating_duration_since(self.last_scan)).unwrap_or_default();
+        self.last_scan = now;
+
+        // games' limits are daily, with or without a session
+        let today = self.clock.now().date_naive();
+        let passed_midnight = today > self.day
+            || self.gaming_session.as_ref().is_some_and(DailyGamingSession::is_passed_midnight);
 
-            if time_played_tracker.is_session_over(time_played) {
-                time_played_tracker.end_session();
+        if passed_midnight {
+            // the part of the interval before midnight is played yesterday
+            let after_midnight = self.clock.now()
+                .signed_duration_since(end_of_local_day(self.day)?)
+                .clamp(chrono::Duration::zero(), elapsed);
+            self.update_running_games(elapsed - after_midnight);
+
+            if let Some(time_played_tracker) = self.gaming_session.as_mut() {
+                time_played_tracker.restart_session(self.total_time_played)?;
             }
+
+            self.ended_day = Some(PlayedDay {
+                day: self.day,
+                time_played: self.total_time_played,
+                games: self.games.iter()
+                    .map(|(game, game_time)| (game.clone(), game_time.time_played))
+                    .collect(),
+            });
+
+            // a new day starts : yesterday's time doesn't count
+            self.day = today.max(self.day);
+            self.total_time_played = chrono::Duration::zero();
+            self.games.retain(|_, game_time| game_time.is_running());
+            self.games.values_mut().for_each(|game_time| game_time.time_played = chrono::Duration::zero());
+            self.update_running_games(after_midnight);
+        } else {
+            self.update_running_games(elapsed);
+        }
+
+        if let Some(time_played_tracker) = self.gaming_session.as_mut()
+            && time_played_tracker.is_session_over(self.total_time_played) {
+            time_played_tracker.end_session();
         }
 
         Ok(())
     }
 
-    fn update_running_games(&mut self) {
-        for (_, process) in self.process_snapshots.iter() {
-            if let Some((game_name, game_process)) = find_game(process, &self.installed_games) {
-                let running_games = self.games.entry(game_name)
-                    .or_insert(HashSet::new());
+    /// Find the games running and add the time `elapsed` since the previous scan to their time
+    /// played : the whole interval when a game was already running at the previous scan, the
+    /// run time of its process when it started since. Games exiting between two scans aren't
+    /// credited for that interval.
+    #[allow(clippy::mutable_key_type)]
+    fn update_running_games(&mut self, elapsed: chrono::Duration) {
+        let mut running: BTreeMap<&Game, HashSet<ProcessInfo>> = BTreeMap::new();
+        for (game, game_process, _) in self.game_index.find_all(&self.process_snapshots) {
+            running.entry(game).or_default().insert(game_process.clone());
+        }
 
-                if running_games.contains(&game_process) {
-                    // if process is already present, remove it to insert it again (with updated runtime)
-                    // ths is 100% a hack
-                    running_games.remove(&game_process);
-                }
+        for game_time in self.games.values_mut() {
+            game_time.processes.clear();
+        }
 
-                running_games.insert(game_process.clone());
-            }
+        let mut played = chrono::Duration::zero();
+        for (game, processes) in running {
+            let game_time = self.games.entry(game.clone()).or_default();
+            let credited = if game_time.is_running() {
+                elapsed
+            } else {
+                let run_time = processes.iter().map(ProcessInfo::run_time).max().unwrap_or_default();
+                elapsed.min(chrono::Duration::seconds(run_time as i64))
+            };
+
+            game_time.time_played += credited;
+            game_time.processes = processes;
+            played = played.max(credited);
         }
+
+        self.total_time_played += played;
     }
 
     #[check_tampering]
     pub fn kill(&self, p: &ProcessInfo) -> Result<bool, Error> {
-        match self.system_processes.process(p.pid()) {
-            Some(p) => Ok(p.kill()),
-            None => Ok(true)
-        }
+        Ok(self.process_source.kill(p.pid()))
     }
 }
 
+
+#[cfg(test)]
+mod game_locator_tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    #[test]
+    fn test_steam_libraries() {
+        let steam = TempDir::new("steam");
+        let library = TempDir::new("steam-library");
+        let missing = library.path().join("removed-disk");
+
+        steam.write("steamapps/libraryfolders.vdf", &format!(r#"
+            "libraryfolders"
+            {{
+                "0" {{ "path" "{}" "label" "" }}
+                "1" {{ "path" "{}" }}
+                "2" "{}"
+                "3" {{ "path" "relative/library" }}
+            }}
+        "#, steam.path().display(), library.path().display(), missing.display()));
+
+        steam.write("steamapps/appmanifest_620.acf", r#"
+            "AppState"
+            {
+                "appid" "620"
+                "name" "Portal 2"
+                "installdir" "Portal 2"
+            }
+        "#);
+        steam.write("steamapps/appmanifest_228980.acf", r#"
+            "AppState" { "appid" "228980" "name" "Steamworks Common Redistributables" }
+        "#);
+        steam.write("steamapps/libraryfolders.acf", r#""AppState" { "appid" "1" "name" "Not a manifest" "installdir" "x" }"#);
+        library.write("steamapps/appmanifest_504230.acf", r#"
+            "AppState" { "appid" "504230" "name" "Celeste" "installdir" "Celeste" }
+        "#);
+
+        let libraries = steam_libraries(steam.path());
+        assert_eq!(libraries, vec![
+            steam.path().to_path_buf(),
+            steam.path().to_path_buf(),
+            library.path().to_path_buf(),
+            missing.clone(),
+        ]);
+
+        let games = libraries.iter()
+            .skip(1)
+            .flat_map(|library| steam_library_games(library))
+            .map(|game| (game.id().map(str::to_string), game.name().to_string(), game.install_dir.clone()))
+            .collect::<Vec<_>>();
+
+        assert_eq!(games, vec![
+            (Some("620".to_string()), "Portal 2".to_string(), Some(steam.path().join("steamapps/common/Portal 2"))),
+            (Some("504230".to_string()), "Celeste".to_string(), Some(library.path().join("steamapps/common/Celeste"))),
+        ]);
+        assert!(steam_library_games(&missing).is_empty());
+    }
+
+    #[test]
+    fn test_desktop_games() {
+        let applications = TempDir::new("applications");
+        applications.write("org.supertuxproject.SuperTux.desktop",
+            "[Desktop Entry]\nType=Application\nName=SuperTux\nCategories=Game;\n\
+             Exec=/usr/bin/flatpak run --branch=stable --command=supertux2 org.supertuxproject.SuperTux\n");
+        applications.write("com.valvesoftware.Steam.desktop",
+            "[Desktop Entry]\nType=Application\nName=Steam\nCategories=Network;Game;\n\
+             Exec=/usr/bin/flatpak run --branch=stable --arch=x86_64 --command=/app/bin/steam-wrapper \
+             --file-forwarding com.valvesoftware.Steam @@u %U @@\n");
+        applications.write("steam-portal.desktop",
+            "[Desktop Entry]\nType=Application\nName=Portal\nCategories=Game;\nExec=steam steam://rungameid/400\n");
+
+        let mut games = vec![];
+        desktop_games(applications.path(), "", &["com.valvesoftware.Steam".to_string()], &mut games);
+
+        let found: Vec<(Option<&str>, &str)> = games.iter().map(|game| (game.id(), game.name())).collect();
+        assert_eq!(found, vec![(Some("org.supertuxproject.SuperTux"), "SuperTux")]);
+    }
+
+    #[test]
+    fn test_platform_substring_match() {
+        let launcher = ProcessInfo::fake(1, "lutris", &["lutris", "lutris:rungame/Celeste"]);
+        let celeste = Game::installed_in(Some("celeste"), "Celeste", Path::new("/games/Celeste"));
+        assert!(celeste.matcher().matching_rule(&launcher, Precision::Substring).is_none());
+
+        let mut platform: GameLocator = toml::from_str(r#"
+            home_paths = [".local/share/lutris/pga.db"]
+            locator_type = "LUTRIS"
+            matchers = { substring_match = true }
+        "#).expect("valid platform");
+        platform.games = vec![celeste, Game::from_name("Hades").with_executable(PathBuf::from("/opt/Hades/Hades"))];
+        platform.configure_matchers().expect("no errors!");
+
+        assert!(platform.games.iter().all(|game| game.matcher().substring_enabled()));
+        assert!(platform.games[0].matcher().matching_rule(&launcher, Precision::Substring).is_some());
+    }
+
+    #[test]
+    fn test_heroic_stores() {
+        let config = TempDir::new("heroic");
+
+        config.write("legendaryConfig/legendary/installed.json", r#"{
+            "Fortnite": {
+                "app_name": "Fortnite",
+                "title": "Fortnite",
+                "install_path": "/mnt/games/Fortnite",
+                "executable": "FortniteGame/Binaries/Win64/FortniteLauncher.exe",
+                "platform": "Windows"
+            }
+        }"#);
+        config.write("gog_store/installed.json", r#"{
+            "installed": [
+                { "appName": "1207658924", "install_path": "/home/user/Games/Heroic/Unreal Gold", "platform": "windows" },
+                { "appName": "1", "install_path": "/home/user/Games/Heroic/Unknown Game", "platform": "linux" }
+            ]
+        }"#);
+        config.write("gog_store/library.json", r#"{
+            "games": [ { "app_name": "1207658924", "title": "Unreal Gold" } ]
+        }"#);
+        config.write("nile_config/nile/installed.json", r#"[
+            { "id": "amzn1.adg.product.1", "path": "/home/user/Games/Heroic/Amazon Game", "version": "1" }
+        ]"#);
+
+        let mut games = heroic_games(config.path());
+        games.sort();
+
+        let names: Vec<&str> = games.iter().map(|g| g.name()).collect();
+        assert_eq!(names, vec!["Amazon Game", "Fortnite", "Unknown Game", "Unreal Gold"]);
+
+        let fortnite = &games[1];
+        assert_eq!(fortnite.id(), Some("Fortnite"));
+        assert_eq!(
+            fortnite.executable.as_deref(),
+            Some(Path::new("/mnt/games/Fortnite/FortniteGame/Binaries/Win64/FortniteLauncher.exe"))
+        );
+        let launcher = ProcessInfo::fake(
+            1, "FortniteLaunche", &[r"C:\Program Files\Epic\FortniteLauncher.exe"]
+        );
+        assert!(fortnite.matcher().matching_rule(&launcher, Precision::Exact).is_some());
+    }
+
+    #[test]
+    fn test_lutris_database() {
+        let directory = TempDir::new("lutris");
+        let database = directory.path().join("pga.db");
+
+        let conn = Connection::open(&database).unwrap();
+        conn.execute_batch("
+            CREATE TABLE games (slug TEXT, name TEXT, runner TEXT, directory TEXT, installed INTEGER);
+            INSERT INTO games VALUES ('osu', 'osu!', 'wine', '/home/user/Games/osu', 1);
+            INSERT INTO games VALUES ('portal', 'Portal', 'steam', NULL, 1);
+            INSERT INTO games VALUES ('doom', 'DOOM', 'linux', NULL, 1);
+            INSERT INTO games VALUES ('quake', 'Quake', 'linux', '/home/user/Games/quake', 0);
+        ").unwrap();
+        drop(conn);
+
+        let games = lutris_games(&database).expect("no errors!");
+
+        assert_eq!(games.len(), 1);
+        assert_eq!(games[0].id(), Some("osu"));
+        assert_eq!(games[0].name(), "osu!");
+        assert_eq!(games[0].install_dir.as_deref(), Some(Path::new("/home/user/Games/osu")));
+    }
+
+    #[test]
+    fn test_flatpak_search_paths() {
+        let home = Path::new("/home/user");
+        let paths = flatpak_search_paths(home, "com.heroicgameslauncher.hgl", Path::new(".config/heroic"));
+        let found: Vec<&Path> = paths.iter().map(|p| p.path.as_path()).collect();
+
+        assert_eq!(found, vec![
+            Path::new("/home/user/.var/app/com.heroicgameslauncher.hgl/.config/heroic"),
+            Path::new("/home/user/.var/app/com.heroicgameslauncher.hgl/config/heroic"),
+        ]);
+
+        let paths = flatpak_search_paths(home, "com.valvesoftware.Steam", Path::new(".local/share/Steam"));
+        let sandbox = paths[0].sandbox.as_ref().expect("persisted path is sandboxed");
+        assert_eq!(
+            sandbox.to_sandbox(Path::new(
+                "/home/user/.var/app/com.valvesoftware.Steam/.local/share/Steam/steamapps/common/Portal"
+            )),
+            Some(PathBuf::from("/home/user/.local/share/Steam/steamapps/common/Portal"))
+        );
+        assert_eq!(
+            sandbox.to_host(Path::new("/mnt/games/SteamLibrary")),
+            PathBuf::from("/mnt/games/SteamLibrary")
+        );
+    }
+
+    #[test]
+    fn test_manual_games_config() {
+        let config = Config::parse(r#"
+            [[games]]
+            name = "Minecraft"
+            category = "sandbox"
+            limit = "1h 30m"
+            cmd_regexes = ["net\\.minecraft"]
+
+            [[games]]
+            name = "Portal"
+
+            [steam]
+            home_paths = [".local/share/Steam"]
+            locator_type = "STEAM"
+        "#).expect("valid configuration");
+
+        assert_eq!(config.games.len(), 2);
+        assert!(config.platforms.contains_key("steam"));
+
+        let minecraft = config.games[0].to_game().expect("valid matchers");
+        assert_eq!(minecraft.category(), Some("sandbox"));
+        assert_eq!(minecraft.limit(), Some(chrono::Duration::minutes(90)));
+
+        let client = ProcessInfo::fake(1, "java", &["java", "net.minecraft.client.main.Main"]);
+        let other_java = ProcessInfo::fake(2, "java", &["java", "-jar", "idea.jar"]);
+        assert!(matches!(minecraft.matcher().matching_rule(&client, Precision::Exact), Some(Rule::CmdRegex(_))));
+        assert!(minecraft.matcher().matching_rule(&other_java, Precision::Exact).is_none());
+        assert!(minecraft.matcher().matching_rule(&other_java, Precision::Substring).is_none());
+
+        let portal = config.games[1].to_game().expect("valid matchers");
+        let steam_portal = Game::installed_in(Some("400"), "portal", Path::new("/games/Portal"));
+        assert!(portal.conflicts_with(&steam_portal));
+    }
+
+}
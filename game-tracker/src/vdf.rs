@@ -0,0 +1,235 @@
+use std::iter::Peekable;
+use std::str::Chars;
+use crate::errors::Error;
+
+/// VdfValue represents a node of a Valve KeyValues (VDF) document.
+/// Keys are kept in the order they were found and lookups are case-insensitive
+/// (Steam isn't consistent with key casing between versions).
+#[derive(Debug, Clone, PartialEq)]
+pub enum VdfValue {
+    String(String),
+    Object(Vec<(String, VdfValue)>),
+}
+
+impl VdfValue {
+
+    /// Get the value associated to `key` (only works on objects)
+    pub fn get(&self, key: &str) -> Option<&VdfValue> {
+        match self {
+            VdfValue::Object(entries) => entries.iter()
+                .find(|(k, _)| k.eq_ignore_ascii_case(key))
+                .map(|(_, v)| v),
+            VdfValue::String(_) => None
+        }
+    }
+
+    /// Get the string associated to `key` (only works on objects)
+    pub fn get_str(&self, key: &str) -> Option<&str> {
+        self.get(key).and_then(VdfValue::as_str)
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            VdfValue::String(s) => Some(s),
+            VdfValue::Object(_) => None
+        }
+    }
+
+    pub fn entries(&self) -> &[(String, VdfValue)] {
+        match self {
+            VdfValue::Object(entries) => entries,
+            VdfValue::String(_) => &[]
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Text(String),
+    Open,
+    Close,
+}
+
+struct Tokenizer<'a> {
+    chars: Peekable<Chars<'a>>,
+}
+
+impl<'a> Tokenizer<'a> {
+
+    fn new(input: &'a str) -> Self {
+        Tokenizer { chars: input.chars().peekable() }
+    }
+
+    fn skip_whitespace_and_comments(&mut self) {
+        while let Some(c) = self.chars.peek() {
+            if c.is_whitespace() {
+                self.chars.next();
+            } else if *c == '/' {
+                // comments go until the end of the line
+                self.chars.next();
+                if self.chars.peek() == Some(&'/') {
+                    for c in self.chars.by_ref() {
+                        if c == '\n' {
+                            break;
+                        }
+                    }
+                }
+            } else if *c == '[' {
+                // conditionals (ex.: [$WIN32]) are ignored
+                for c in self.chars.by_ref() {
+                    if c == ']' {
+                        break;
+                    }
+                }
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn quoted(&mut self) -> Result<String, Error> {
+        let mut value = String::new();
+
+        while let Some(c) = self.chars.next() {
+            match c {
+                '"' => return Ok(value),
+                '\\' => match self.chars.next() {
+                    Some('n') => value.push('\n'),
+                    Some('t') => value.push('\t'),
+                    Some(escaped) => value.push(escaped),
+                    None => break,
+                },
+                _ => value.push(c),
+            }
+        }
+
+        Err(Error::VDFParseError("unterminated string".to_string()))
+    }
+
+    fn unquoted(&mut self) -> String {
+        let mut value = String::new();
+
+        while let Some(c) = self.chars.peek() {
+            if c.is_whitespace() || matches!(c, '{' | '}' | '"') {
+                break;
+            }
+
+            value.push(*c);
+            self.chars.next();
+        }
+
+        value
+    }
+
+    fn next_token(&mut self) -> Result<Option<Token>, Error> {
+        self.skip_whitespace_and_comments();
+
+        match self.chars.peek() {
+            None => Ok(None),
+            Some('{') => {
+                self.chars.next();
+                Ok(Some(Token::Open))
+            },
+            Some('}') => {
+                self.chars.next();
+                Ok(Some(Token::Close))
+            },
+            Some('"') => {
+                self.chars.next();
+                Ok(Some(Token::Text(self.quoted()?)))
+            },
+            Some(_) => Ok(Some(Token::Text(self.unquoted()))),
+        }
+    }
+}
+
+fn parse_object(tokenizer: &mut Tokenizer, nested: bool) -> Result<VdfValue, Error> {
+    let mut entries = vec![];
+
+    loop {
+        let key = match tokenizer.next_token()? {
+            Some(Token::Text(key)) => key,
+            Some(Token::Close) if nested => return Ok(VdfValue::Object(entries)),
+            None if !nested => return Ok(VdfValue::Object(entries)),
+            other => return Err(Error::VDFParseError(format!("unexpected token {:?}", other))),
+        };
+
+        let value = match tokenizer.next_token()? {
+            Some(Token::Text(value)) => VdfValue::String(value),
+            Some(Token::Open) => parse_object(tokenizer, true)?,
+            other => return Err(Error::VDFParseError(
+                format!("unexpected token {:?} after key \"{}\"", other, key)
+            )),
+        };
+
+        entries.push((key, value));
+    }
+}
+
+/// Parse a VDF document (ex.: libraryfolders.vdf, appmanifest_*.acf).
+/// The returned value is an object containing the root key(s) of the document.
+pub fn parse(input: &str) -> Result<VdfValue, Error> {
+    parse_object(&mut Tokenizer::new(input), false)
+}
+
+#[cfg(test)]
+mod vdf_parser_tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_app_manifest() {
+        let document = parse(r#"
+            "AppState"
+            {
+                "appid"		"620"
+                "name"		"Portal 2"
+                "installdir"		"Portal 2"
+                "UserConfig"
+                {
+                    "language"		"english"
+                }
+            }
+        "#).expect("no errors!");
+
+        let app_state = document.get("appstate").expect("root key exists");
+        assert_eq!(app_state.get_str("appid"), Some("620"));
+        assert_eq!(app_state.get_str("name"), Some("Portal 2"));
+        assert_eq!(app_state.get_str("InstallDir"), Some("Portal 2"));
+        assert_eq!(
+            app_state.get("UserConfig").and_then(|c| c.get_str("language")),
+            Some("english")
+        );
+    }
+
+    #[test]
+    fn test_parse_escapes_and_comments() {
+        let document = parse(r#"
+            // comment
+            "libraryfolders"
+            {
+                "0" { "path" "C:\\Program Files (x86)\\Steam" } [$WIN32]
+                "1" "/mnt/games"
+            }
+        "#).expect("no errors!");
+
+        let folders = document.get("libraryfolders").expect("root key exists");
+        assert_eq!(folders.entries().len(), 2);
+        assert_eq!(
+            folders.get("0").and_then(|f| f.get_str("path")),
+            Some(r"C:\Program Files (x86)\Steam")
+        );
+        assert_eq!(folders.get_str("1"), Some("/mnt/games"));
+    }
+
+    #[test]
+    fn test_parse_invalid_documents() {
+        parse(r#""AppState" { "appid" "620" "#)
+            .expect_err("should not work!");
+
+        parse(r#""AppState" { "appid }"#)
+            .expect_err("should not work!");
+
+        parse(r#""AppState" } "#)
+            .expect_err("should not work!");
+    }
+}
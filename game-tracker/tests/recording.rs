@@ -0,0 +1,56 @@
+use chrono::{Local, TimeZone};
+use game_tracker::clock::Clock;
+use game_tracker::errors::Error;
+use game_tracker::process_source::ProcessSource;
+use game_tracker::process_tree::{ProcessInfo, ProcessTree};
+use game_tracker::recording::{RecordingSource, ReplaySource};
+use support::{FakeProcessSource, TempDir};
+
+mod support;
+
+#[test]
+fn test_record_and_replay() {
+    let directory = TempDir::new("recording");
+    let path = directory.path().join("recording.jsonl");
+    let processes = FakeProcessSource::new();
+    let clock = processes.clock();
+    let mut recording = RecordingSource::create(&path, Box::new(processes.clone()), clock.clone())
+        .expect("no errors!");
+
+    processes.spawn(10, None, "steam", &["/usr/bin/steam"]);
+    processes.spawn(11, Some(10), "portal2_linux", &["/games/Portal 2/portal2_linux"]);
+    let recorded = [
+        recording.refresh().expect("no errors!"),
+        {
+            processes.advance(60);
+            processes.exit(11);
+            processes.spawn(12, Some(10), "Celeste", &["/opt/Celeste/Celeste"]);
+            recording.refresh().expect("no errors!")
+        },
+        {
+            processes.advance(60);
+            recording.refresh().expect("no errors!")
+        },
+    ];
+    drop(recording);
+
+    let content = std::fs::read_to_string(&path).expect("no errors!");
+    assert_eq!(content.lines().count(), 3);
+    assert!(content.lines().last().is_some_and(|line| !line.contains("started")));
+
+    let mut replay = ReplaySource::open(&path).expect("no errors!");
+    let replay_clock = replay.clock();
+    assert_eq!(replay_clock.now(), Local.with_ymd_and_hms(2026, 1, 10, 12, 0, 0).unwrap());
+
+    for tree in recorded {
+        let replayed = replay.refresh().expect("no errors!");
+        let pids = |tree: &ProcessTree| tree.iter()
+            .map(|p| (p.pid(), tree.parent(p.pid()).map(ProcessInfo::pid), p.cmd(), p.run_time()))
+            .collect::<Vec<_>>();
+
+        assert_eq!(pids(&replayed), pids(&tree));
+    }
+
+    assert_eq!(replay_clock.now(), clock.now());
+    assert!(matches!(replay.refresh(), Err(Error::EndOfRecordingError)));
+}
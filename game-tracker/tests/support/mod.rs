@@ -0,0 +1,163 @@
+//! Helpers shared by the integration tests
+#![allow(dead_code)]
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::time::Duration;
+use chrono::{Local, TimeZone};
+use sysinfo::Pid;
+use game_tracker::clock::{Clock, ManualClock};
+use game_tracker::errors::Error;
+use game_tracker::notifier::Notifier;
+use game_tracker::process_source::ProcessSource;
+use game_tracker::process_tree::{ProcessInfo, ProcessTree};
+
+#[path = "../../src/test_support.rs"]
+mod test_support;
+
+pub use test_support::TempDir;
+
+/// Processes of the fake source : process, parent and start time (seconds since epoch)
+#[derive(Debug)]
+struct FakeProcesses {
+    processes: BTreeMap<Pid, (ProcessInfo, Option<Pid>, u64)>,
+    killed: Vec<Pid>,
+    clock: Rc<ManualClock>,
+}
+
+/// Scripted in-memory processes, used to test the tracker without real processes.
+/// Clones share the same processes : tests keep a clone to add, age and remove processes
+/// between scans while the tracker owns the other one. Time only passes with `advance`
+/// (on the processes and on `clock`).
+#[derive(Debug, Clone)]
+pub struct FakeProcessSource {
+    state: Rc<RefCell<FakeProcesses>>,
+}
+
+impl FakeProcessSource {
+
+    /// Source without processes, starting on 2026-01-10 at noon (local time)
+    pub fn new() -> Self {
+        let start = Local.with_ymd_and_hms(2026, 1, 10, 12, 0, 0).earliest()
+            .expect("noon exists in every timezone");
+
+        FakeProcessSource {
+            state: Rc::new(RefCell::new(FakeProcesses {
+                processes: BTreeMap::new(),
+                killed: vec![],
+                clock: ManualClock::new(start),
+            })),
+        }
+    }
+
+    /// Clock of the processes (give it to the tracker and sessions)
+    pub fn clock(&self) -> Rc<ManualClock> {
+        self.state.borrow().clock.clone()
+    }
+
+    fn now(&self) -> u64 {
+        self.state.borrow().clock.now().timestamp() as u64
+    }
+
+    /// Start a process (its executable is the first argument if it's an absolute path)
+    pub fn spawn(&self, pid: u32, parent: Option<u32>, name: &str, cmd: &[&str]) {
+        let exe = cmd.first()
+            .map(PathBuf::from)
+            .filter(|exe| exe.is_absolute());
+        let process = ProcessInfo::new(
+            Pid::from_u32(pid),
+            name.to_string(),
+            cmd.iter().map(|arg| arg.to_string()).collect(),
+            None,
+            exe,
+            None,
+            None,
+        );
+
+        self.spawn_process(process, parent);
+    }
+
+    /// Start a process built by the test (environment, working directory...)
+    pub fn spawn_process(&self, process: ProcessInfo, parent: Option<u32>) {
+        let now = self.now();
+        let mut state = self.state.borrow_mut();
+        state.processes.insert(process.pid(), (process, parent.map(Pid::from_u32), now));
+    }
+
+    /// Stop a process (its children are orphaned, like real processes)
+    pub fn exit(&self, pid: u32) {
+        self.state.borrow_mut().processes.remove(&Pid::from_u32(pid));
+    }
+
+    /// Let `seconds` pass (every running process gets older)
+    pub fn advance(&self, seconds: u64) {
+        self.clock().advance(Duration::from_secs(seconds));
+    }
+
+    pub fn is_running(&self, pid: u32) -> bool {
+        self.state.borrow().processes.contains_key(&Pid::from_u32(pid))
+    }
+
+    /// Processes killed by the tracker (in order)
+    pub fn killed(&self) -> Vec<u32> {
+        self.state.borrow().killed.iter().map(|pid| pid.as_u32()).collect()
+    }
+}
+
+impl Default for FakeProcessSource {
+    fn default() -> Self {
+        FakeProcessSource::new()
+    }
+}
+
+impl ProcessSource for FakeProcessSource {
+
+    fn refresh(&mut self) -> Result<ProcessTree, Error> {
+        let now = self.now();
+        let state = self.state.borrow();
+        Ok(ProcessTree::build(state.processes.values()
+            .map(|(process, parent, started)| {
+                let mut process = process.clone();
+                process.set_times(*started, now.saturating_sub(*started));
+                (process, *parent)
+            })
+            .collect()))
+    }
+
+    fn kill(&self, pid: Pid) -> bool {
+        let mut state = self.state.borrow_mut();
+        if state.processes.remove(&pid).is_some() {
+            state.killed.push(pid);
+        }
+        true
+    }
+}
+
+/// Notifications sent by the tracker (tests run without a notification daemon)
+#[derive(Debug, Default)]
+pub struct FakeNotifier {
+    messages: RefCell<Vec<String>>,
+}
+
+impl FakeNotifier {
+
+    pub fn new() -> Rc<Self> {
+        Rc::new(FakeNotifier::default())
+    }
+
+    /// Messages notified so far (in order)
+    pub fn messages(&self) -> Vec<String> {
+        self.messages.borrow().clone()
+    }
+}
+
+impl Notifier for FakeNotifier {
+
+    fn notify(&self, msg: &str) -> Result<(), Error> {
+        println!("Notification : {}", msg);
+        self.messages.borrow_mut().push(msg.to_string());
+        Ok(())
+    }
+}
@@ -0,0 +1,516 @@
+use std::path::Path;
+use chrono::Duration;
+use game_tracker::clock::{Clock, SharedClock};
+use game_tracker::config::Config;
+use game_tracker::db::SaveStatistics;
+use game_tracker::notifier::SharedNotifier;
+use game_tracker::process_source::ProcessSource;
+use game_tracker::recording::{RecordingSource, ReplaySource};
+use game_tracker::scheduler::GameTrackerScheduler;
+use game_tracker::session::{BudgetPeriod, DailyGamingSession};
+use game_tracker::subtasks::{ClockTampering, CurfewGameKiller, GameLimitKiller, SessionEndGameKiller};
+use game_tracker::tracker::GamingTracker;
+use support::{FakeNotifier, FakeProcessSource, TempDir};
+
+mod support;
+
+const CONFIG: &str = r#"
+    [[games]]
+    name = "Portal 2"
+    exe_prefixes = ["/games/Portal 2"]
+
+    [[games]]
+    name = "Celeste"
+    limit = "20m"
+"#;
+
+const STEAM: u32 = 100;
+const REAPER: u32 = 101;
+const PORTAL: u32 = 102;
+const CELESTE: u32 = 200;
+const SHELL: u32 = 300;
+const DESKTOP_SESSION: u32 = 400;
+
+fn scheduler(processes: &FakeProcessSource) -> GameTrackerScheduler {
+    scheduler_with(Box::new(processes.clone()), processes.clock(), FakeNotifier::new(), Duration::zero())
+}
+
+fn scheduler_with(source: Box<dyn ProcessSource>, clock: SharedClock, notifier: SharedNotifier,
+                  scan_interval: Duration) -> GameTrackerScheduler {
+    let mut tracker = GamingTracker::with_source(source, clock.clone()).with_notifier(notifier);
+    tracker.load_config(Config::parse(CONFIG).expect("valid configuration"))
+        .expect("no errors!");
+
+    let scan_interval = scan_interval.to_std().expect("positive scan interval");
+    GameTrackerScheduler::using(scan_interval, tracker, clock)
+}
+
+/// Steam launching Portal 2 through its reaper, and an unrelated shell
+fn launch_portal(processes: &FakeProcessSource) {
+    processes.spawn(SHELL, None, "bash", &["/usr/bin/bash"]);
+    processes.spawn(STEAM, None, "steam", &["/usr/bin/steam"]);
+    processes.spawn(REAPER, Some(STEAM), "reaper", &["reaper", "SteamLaunch", "AppId=620"]);
+    processes.spawn(PORTAL, Some(REAPER), "portal2_linux", &["/games/Portal 2/portal2_linux", "-game", "portal2"]);
+}
+
+fn running_pids(tracker: &GamingTracker, game: &str) -> Vec<u32> {
+    tracker.gametime_tracker().iter()
+        .filter(|(g, _)| g.name() == game)
+        .flat_map(|(_, game_time)| game_time.processes().iter().map(|p| p.pid().as_u32()))
+        .collect()
+}
+
+fn time_played(tracker: &GamingTracker, game: &str) -> Duration {
+    tracker.gametime_tracker().keys()
+        .find(|g| g.name() == game)
+        .map(|g| tracker.time_played(g))
+        .unwrap_or_default()
+}
+
+#[test]
+fn test_game_detection() {
+    let processes = FakeProcessSource::new();
+    let mut scheduler = scheduler(&processes);
+
+    scheduler.tick().expect("no errors!");
+    assert!(scheduler.tracker().gametime_tracker().is_empty());
+
+    launch_portal(&processes);
+    processes.spawn(CELESTE, None, "Celeste", &["/opt/Celeste/Celeste"]);
+    scheduler.tick().expect("no errors!");
+
+    assert_eq!(running_pids(scheduler.tracker(), "Portal 2"), vec![PORTAL]);
+    assert_eq!(running_pids(scheduler.tracker(), "Celeste"), vec![CELESTE]);
+    assert_eq!(scheduler.tracker().gametime_tracker().len(), 2);
+}
+
+#[test]
+fn test_time_accounting() {
+    let processes = FakeProcessSource::new();
+    let mut scheduler = scheduler(&processes);
+
+    launch_portal(&processes);
+    scheduler.tick().expect("no errors!");
+    assert_eq!(time_played(scheduler.tracker(), "Portal 2"), Duration::zero());
+
+    processes.advance(10 * 60);
+    scheduler.tick().expect("no errors!");
+    assert_eq!(time_played(scheduler.tracker(), "Portal 2"), Duration::minutes(10));
+
+    // time played is kept once the game exits
+    processes.exit(PORTAL);
+    processes.advance(10 * 60);
+    scheduler.tick().expect("no errors!");
+    assert_eq!(time_played(scheduler.tracker(), "Portal 2"), Duration::minutes(10));
+    assert_eq!(scheduler.tracker().total_time_played(), Duration::minutes(10));
+}
+
+#[test]
+fn test_overlapping_games() {
+    let processes = FakeProcessSource::new();
+    let mut scheduler = scheduler(&processes);
+
+    // two Celeste processes and Portal 2 (with its launcher) at the same time
+    launch_portal(&processes);
+    processes.spawn(CELESTE, None, "Celeste", &["/opt/Celeste/Celeste"]);
+    processes.spawn(CELESTE + 1, None, "Celeste", &["/opt/Celeste/Celeste", "--safe-mode"]);
+    scheduler.tick().expect("no errors!");
+
+    processes.advance(10 * 60);
+    scheduler.tick().expect("no errors!");
+
+    assert_eq!(time_played(scheduler.tracker(), "Celeste"), Duration::minutes(10));
+    assert_eq!(time_played(scheduler.tracker(), "Portal 2"), Duration::minutes(10));
+    assert_eq!(scheduler.tracker().total_time_played(), Duration::minutes(10));
+}
+
+#[test]
+fn test_games_in_one_desktop_session() {
+    let processes = FakeProcessSource::new();
+    let mut scheduler = scheduler(&processes);
+    scheduler.add_gaming_session(DailyGamingSession::from_duration(Duration::minutes(30), processes.clock()).expect("no errors!"));
+    scheduler.add(GameLimitKiller::new());
+    scheduler.add(SessionEndGameKiller::new());
+
+    // both games are started from the same `systemd --user` session
+    let session = Some(DESKTOP_SESSION);
+    processes.spawn(DESKTOP_SESSION, None, "systemd", &["/usr/lib/systemd/systemd", "--user"]);
+    processes.spawn(STEAM, session, "steam", &["/usr/bin/steam"]);
+    processes.spawn(REAPER, Some(STEAM), "reaper", &["reaper", "SteamLaunch", "AppId=620"]);
+    processes.spawn(PORTAL, Some(REAPER), "portal2_linux", &["/games/Portal 2/portal2_linux", "-game", "portal2"]);
+    processes.spawn(CELESTE, session, "Celeste", &["/opt/Celeste/Celeste"]);
+    scheduler.tick().expect("no errors!");
+
+    assert_eq!(running_pids(scheduler.tracker(), "Portal 2"), vec![PORTAL]);
+    assert_eq!(running_pids(scheduler.tracker(), "Celeste"), vec![CELESTE]);
+
+    // Celeste's limit is checked while Portal 2 is running
+    processes.advance(25 * 60);
+    scheduler.tick().expect("no errors!");
+    assert_eq!(time_played(scheduler.tracker(), "Celeste"), Duration::minutes(25));
+    assert_eq!(processes.killed(), vec![CELESTE]);
+
+    processes.spawn(CELESTE + 1, session, "Celeste", &["/opt/Celeste/Celeste"]);
+    processes.advance(10 * 60);
+    scheduler.tick().expect("no errors!");
+    assert!(scheduler.tracker().session().is_some_and(|session| session.is_session_ended()));
+    assert_eq!(processes.killed(), vec![CELESTE, CELESTE + 1, PORTAL]);
+    assert!(processes.is_running(DESKTOP_SESSION));
+}
+
+#[test]
+fn test_restarted_game() {
+    let processes = FakeProcessSource::new();
+    let mut scheduler = scheduler(&processes);
+    scheduler.add_gaming_session(DailyGamingSession::from_duration(Duration::hours(2), processes.clock())
+        .expect("no errors!"));
+
+    processes.spawn(CELESTE, None, "Celeste", &["/opt/Celeste/Celeste"]);
+    scheduler.tick().expect("no errors!");
+    processes.advance(10 * 60);
+    scheduler.tick().expect("no errors!");
+    processes.exit(CELESTE);
+
+    // started 5 minutes before the next scan
+    processes.advance(10 * 60);
+    processes.spawn(CELESTE + 1, None, "Celeste", &["/opt/Celeste/Celeste"]);
+    processes.advance(5 * 60);
+    scheduler.tick().expect("no errors!");
+
+    assert_eq!(running_pids(scheduler.tracker(), "Celeste"), vec![CELESTE + 1]);
+    assert_eq!(time_played(scheduler.tracker(), "Celeste"), Duration::minutes(15));
+    assert_eq!(scheduler.tracker().total_time_played(), Duration::minutes(15));
+
+    // a new day starts at midnight
+    processes.exit(CELESTE + 1);
+    processes.advance(12 * 60 * 60);
+    scheduler.tick().expect("no errors!");
+    assert_eq!(scheduler.tracker().total_time_played(), Duration::zero());
+    assert!(scheduler.tracker().gametime_tracker().is_empty());
+}
+
+#[test]
+fn test_session_end_kills_games() {
+    let processes = FakeProcessSource::new();
+    let mut scheduler = scheduler(&processes);
+    scheduler.add_gaming_session(DailyGamingSession::from_duration(Duration::minutes(30), processes.clock()).expect("no errors!"));
+    scheduler.add(SessionEndGameKiller::new());
+
+    launch_portal(&processes);
+    processes.advance(29 * 60);
+    scheduler.tick().expect("no errors!");
+
+    let session = scheduler.tracker().session().expect("session enabled");
+    assert!(!session.is_session_ended());
+    assert!(processes.killed().is_empty());
+
+    processes.advance(2 * 60);
+    scheduler.tick().expect("no errors!");
+
+    let session = scheduler.tracker().session().expect("session enabled");
+    assert!(session.is_session_ended());
+    assert_eq!(processes.killed(), vec![PORTAL]);
+    assert!(processes.is_running(STEAM));
+    assert!(processes.is_running(SHELL));
+
+    // games started after the end of the session are killed too
+    processes.spawn(CELESTE, None, "Celeste", &["/opt/Celeste/Celeste"]);
+    scheduler.tick().expect("no errors!");
+    assert_eq!(processes.killed(), vec![PORTAL, CELESTE]);
+}
+
+#[test]
+fn test_session_survives_restart() {
+    let directory = TempDir::new("restart");
+    let path = directory.path().join("statistics.sqlite");
+    let processes = FakeProcessSource::new();
+    let session = || DailyGamingSession::from_duration(Duration::minutes(30), processes.clock())
+        .expect("no errors!");
+
+    let mut scheduler = scheduler(&processes);
+    scheduler.add_gaming_session(session());
+    scheduler.add(SaveStatistics::open(&path).expect("no errors!"));
+
+    launch_portal(&processes);
+    scheduler.tick().expect("no errors!");
+    processes.advance(20 * 60);
+    scheduler.tick().expect("no errors!");
+    drop(scheduler);
+
+    // the tracker restarts while the game is still running
+    processes.advance(60);
+    let statistics = SaveStatistics::open(&path).expect("no errors!");
+    let mut scheduler = self::scheduler(&processes);
+    scheduler.add_gaming_session(session());
+    statistics.restore(scheduler.tracker_mut()).expect("no errors!");
+    scheduler.add(statistics);
+
+    scheduler.tick().expect("no errors!");
+    assert_eq!(scheduler.tracker().total_time_played(), Duration::minutes(20));
+
+    processes.advance(15 * 60);
+    scheduler.tick().expect("no errors!");
+    assert!(scheduler.tracker().session().is_some_and(|session| session.is_session_ended()));
+}
+
+#[test]
+fn test_weekly_budget() {
+    let directory = TempDir::new("weekly");
+    let path = directory.path().join("statistics.sqlite");
+    let processes = FakeProcessSource::new();
+    let statistics = SaveStatistics::open(&path).expect("no errors!");
+
+    // 2 hours played earlier this week (the fake processes start on saturday 2026-01-10)
+    let today = processes.clock().now().date_naive();
+    statistics.save_time_played(today - Duration::days(2), Duration::hours(1)).expect("no errors!");
+    statistics.save_time_played(today - Duration::days(1), Duration::hours(1)).expect("no errors!");
+    statistics.save_time_played(today - Duration::days(7), Duration::hours(5)).expect("no errors!");
+
+    let mut scheduler = scheduler(&processes);
+    scheduler.add_gaming_session(DailyGamingSession::new(processes.clock()).expect("no errors!")
+        .with_budget(BudgetPeriod::Daily, Duration::hours(2))
+        .with_budget(BudgetPeriod::Weekly, Duration::minutes(150)));
+    statistics.restore(scheduler.tracker_mut()).expect("no errors!");
+    scheduler.add(statistics);
+    scheduler.add(SessionEndGameKiller::new());
+
+    launch_portal(&processes);
+    scheduler.tick().expect("no errors!");
+    processes.advance(20 * 60);
+    scheduler.tick().expect("no errors!");
+
+    let session = scheduler.tracker().session().expect("session enabled");
+    let binding = session.binding_budget(scheduler.tracker().total_time_played()).expect("budgets set");
+    assert_eq!(binding.period(), BudgetPeriod::Weekly);
+    assert!(!session.is_session_ended());
+
+    processes.advance(15 * 60);
+    scheduler.tick().expect("no errors!");
+    assert!(scheduler.tracker().session().is_some_and(|session| session.is_session_ended()));
+    assert_eq!(processes.killed(), vec![PORTAL]);
+}
+
+#[test]
+fn test_monitor_only_never_kills() {
+    let processes = FakeProcessSource::new();
+    let mut scheduler = scheduler(&processes);
+    scheduler.add_gaming_session(DailyGamingSession::from_duration(Duration::minutes(30), processes.clock()).expect("no errors!"));
+
+    launch_portal(&processes);
+    processes.advance(60 * 60);
+    scheduler.tick().expect("no errors!");
+
+    assert!(scheduler.tracker().session().is_some_and(|session| session.is_session_ended()));
+    assert!(processes.killed().is_empty());
+}
+
+#[test]
+fn test_allowed_hours() {
+    let allowed_hours = Config::parse("[session.allowed_hours]\ndefault = [\"18:00-23:00\"]")
+        .expect("valid configuration")
+        .session.allowed_hours.expect("allowed hours set");
+    let curfew_scheduler = |processes: &FakeProcessSource, monitor_only: bool| {
+        let mut scheduler = scheduler(processes);
+        scheduler.add_gaming_session(DailyGamingSession::new(processes.clock()).expect("no errors!")
+            .with_allowed_hours(allowed_hours.clone()));
+        scheduler.add(CurfewGameKiller::new(monitor_only));
+        scheduler
+    };
+
+    // games are only detected in monitor only mode
+    let processes = FakeProcessSource::new();
+    let mut scheduler = curfew_scheduler(&processes, true);
+    launch_portal(&processes);
+    scheduler.tick().expect("no errors!");
+    assert!(scheduler.tracker().session().is_some_and(|session| session.is_curfew()));
+    assert!(processes.killed().is_empty());
+
+    // the fake clock starts at noon : games are killed until 18:00
+    let processes = FakeProcessSource::new();
+    let mut scheduler = curfew_scheduler(&processes, false);
+    launch_portal(&processes);
+    scheduler.tick().expect("no errors!");
+    assert_eq!(processes.killed(), vec![PORTAL]);
+    assert!(processes.is_running(STEAM));
+
+    processes.advance(6 * 60 * 60);
+    processes.spawn(CELESTE, None, "Celeste", &["/opt/Celeste/Celeste"]);
+    scheduler.tick().expect("no errors!");
+    let session = scheduler.tracker().session().expect("session enabled");
+    assert!(!session.is_curfew());
+    assert_eq!(session.time_left_before_curfew(), Some(Duration::hours(5)));
+    assert_eq!(processes.killed(), vec![PORTAL]);
+
+    processes.advance(5 * 60 * 60);
+    scheduler.tick().expect("no errors!");
+    assert_eq!(processes.killed(), vec![PORTAL, CELESTE]);
+}
+
+#[test]
+fn test_game_limit() {
+    let processes = FakeProcessSource::new();
+    let notifier = FakeNotifier::new();
+    let mut scheduler = scheduler_with(Box::new(processes.clone()), processes.clock(), notifier.clone(), Duration::zero());
+    scheduler.add(GameLimitKiller::new());
+
+    launch_portal(&processes);
+    processes.spawn(CELESTE, None, "Celeste", &["/opt/Celeste/Celeste"]);
+    processes.advance(15 * 60);
+    scheduler.tick().expect("no errors!");
+    assert!(processes.killed().is_empty());
+
+    processes.advance(10 * 60);
+    scheduler.tick().expect("no errors!");
+
+    // only Celeste has a limit
+    assert_eq!(processes.killed(), vec![CELESTE]);
+    assert!(processes.is_running(PORTAL));
+    assert_eq!(notifier.messages(), vec!["Play time's over for Celeste :-)"]);
+}
+
+#[test]
+fn test_game_limit_is_daily() {
+    let processes = FakeProcessSource::new();
+    let mut scheduler = scheduler(&processes);
+    scheduler.add(GameLimitKiller::new());
+
+    processes.spawn(CELESTE, None, "Celeste", &["/opt/Celeste/Celeste"]);
+    processes.advance(15 * 60);
+    scheduler.tick().expect("no errors!");
+    processes.exit(CELESTE);
+
+    // no session : the time played is still reset at midnight
+    processes.advance(12 * 60 * 60);
+    scheduler.tick().expect("no errors!");
+    assert!(scheduler.tracker().gametime_tracker().is_empty());
+
+    processes.spawn(CELESTE, None, "Celeste", &["/opt/Celeste/Celeste"]);
+    processes.advance(10 * 60);
+    scheduler.tick().expect("no errors!");
+    assert_eq!(time_played(scheduler.tracker(), "Celeste"), Duration::minutes(10));
+    assert!(processes.killed().is_empty());
+
+    processes.advance(15 * 60);
+    scheduler.tick().expect("no errors!");
+    assert_eq!(processes.killed(), vec![CELESTE]);
+}
+
+#[test]
+fn test_game_limit_survives_restart() {
+    let directory = TempDir::new("limit-restart");
+    let path = directory.path().join("statistics.sqlite");
+    let processes = FakeProcessSource::new();
+
+    let mut scheduler = scheduler(&processes);
+    scheduler.add(SaveStatistics::open(&path).expect("no errors!"));
+    processes.spawn(CELESTE, None, "Celeste", &["/opt/Celeste/Celeste"]);
+    processes.advance(15 * 60);
+    scheduler.tick().expect("no errors!");
+    drop(scheduler);
+
+    // no session : Celeste's time played is restored for its limit
+    let statistics = SaveStatistics::open(&path).expect("no errors!");
+    let mut scheduler = self::scheduler(&processes);
+    statistics.restore(scheduler.tracker_mut()).expect("no errors!");
+    scheduler.add(statistics);
+    scheduler.add(GameLimitKiller::new());
+    assert_eq!(time_played(scheduler.tracker(), "Celeste"), Duration::minutes(15));
+
+    scheduler.tick().expect("no errors!");
+    processes.advance(10 * 60);
+    scheduler.tick().expect("no errors!");
+    assert_eq!(time_played(scheduler.tracker(), "Celeste"), Duration::minutes(25));
+    assert_eq!(processes.killed(), vec![CELESTE]);
+}
+
+#[test]
+fn test_time_played_across_midnight() {
+    let directory = TempDir::new("midnight");
+    let path = directory.path().join("statistics.sqlite");
+    let processes = FakeProcessSource::new();
+    let yesterday = processes.clock().now().date_naive();
+
+    let mut scheduler = scheduler(&processes);
+    scheduler.add_gaming_session(DailyGamingSession::from_duration(Duration::hours(2), processes.clock())
+        .expect("no errors!"));
+    scheduler.add(SaveStatistics::open(&path).expect("no errors!"));
+
+    // Portal 2 from 23:40 to 00:10, scanned at 23:50 and 00:10
+    processes.advance((11 * 60 + 40) * 60);
+    launch_portal(&processes);
+    scheduler.tick().expect("no errors!");
+    processes.advance(10 * 60);
+    scheduler.tick().expect("no errors!");
+    processes.advance(20 * 60);
+    scheduler.tick().expect("no errors!");
+
+    // the scan after midnight splits its interval between both days
+    assert_eq!(scheduler.tracker().day(), yesterday.succ_opt().expect("valid date"));
+    assert_eq!(scheduler.tracker().total_time_played(), Duration::minutes(10));
+    assert_eq!(time_played(scheduler.tracker(), "Portal 2"), Duration::minutes(10));
+    drop(scheduler);
+
+    let statistics = SaveStatistics::open(&path).expect("no errors!");
+    assert_eq!(statistics.time_played_by_date(yesterday).expect("no errors!"), Duration::minutes(20));
+    let games = statistics.games_played_by_date(yesterday).expect("no errors!");
+    assert_eq!(games, vec![("Portal 2".to_string(), None, Duration::minutes(20))]);
+}
+
+/// Record 40 minutes of Portal 2, scanned every 10 minutes
+fn record_portal(path: &Path) -> FakeProcessSource {
+    let processes = FakeProcessSource::new();
+    let mut recording = RecordingSource::create(path, Box::new(processes.clone()), processes.clock())
+        .expect("no errors!");
+
+    launch_portal(&processes);
+    for _ in 0..5 {
+        recording.refresh().expect("no errors!");
+        processes.advance(10 * 60);
+    }
+
+    processes
+}
+
+/// Scheduler replaying the recording at `path` (and the clock of the replay)
+fn replay_scheduler(path: &Path, scan_interval: Duration) -> (GameTrackerScheduler, SharedClock) {
+    let replay = ReplaySource::open(path).expect("no errors!");
+    let replay_clock: SharedClock = replay.clock();
+    let mut scheduler = scheduler_with(Box::new(replay), replay_clock.clone(), FakeNotifier::new(), scan_interval);
+    scheduler.add(ClockTampering::new(replay_clock.clone()));
+    (scheduler, replay_clock)
+}
+
+#[test]
+fn test_replay() {
+    let directory = TempDir::new("replay");
+    let path = directory.path().join("replay.jsonl");
+    let processes = record_portal(&path);
+
+    let (mut scheduler, replay_clock) = replay_scheduler(&path, Duration::zero());
+    scheduler.add_gaming_session(DailyGamingSession::from_duration(Duration::minutes(30), replay_clock)
+        .expect("no errors!"));
+    scheduler.add(SessionEndGameKiller::new());
+
+    // the scheduler stops at the end of the recording
+    scheduler.start().expect("no errors!");
+
+    assert!(scheduler.tracker().session().is_some_and(|session| session.is_session_ended()));
+    assert_eq!(time_played(scheduler.tracker(), "Portal 2"), Duration::minutes(40));
+    assert!(processes.killed().is_empty());
+}
+
+#[test]
+fn test_replay_with_scan_interval() {
+    let directory = TempDir::new("replay-interval");
+    let path = directory.path().join("replay.jsonl");
+    record_portal(&path);
+
+    // waiting between scans doesn't add time : the replay only follows the recorded times
+    for minutes in [5, 15] {
+        let (mut scheduler, _) = replay_scheduler(&path, Duration::minutes(minutes));
+        scheduler.start().expect("no errors!");
+
+        assert_eq!(scheduler.tracker().total_time_played(), Duration::minutes(40));
+        assert_eq!(time_played(scheduler.tracker(), "Portal 2"), Duration::minutes(40));
+    }
+}
@@ -12,7 +12,7 @@ pub fn check_tampering(_attr: TokenStream, item: TokenStream) -> TokenStream {
 
     if sig.asyncness.is_some() {
         return syn::Error::new_spanned(
-            &sig.asyncness,
+            sig.asyncness,
             "async functions are not supported"
         ).to_compile_error().into()
     }